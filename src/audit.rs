@@ -0,0 +1,190 @@
+//! Audit log of control actions taken on the system.
+
+use crate::state;
+use rocket::http::Status;
+use rocket::serde::json::{serde_json, Value};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, metadata, read_to_string, rename, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+const AUDIT_LOG: &str = "audit.log";
+/// Size in bytes after which the audit log is rotated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Amount of rotated audit logs to keep.
+const MAX_ROTATED_FILES: usize = 4;
+
+/// Serializes writes to and rotations of the audit log.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Type of an audited control action.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Kind {
+    /// An RPC command received via `/rpc`.
+    #[serde(rename = "rpc")]
+    Rpc,
+    /// A configuration received via `/configure`.
+    #[serde(rename = "configure")]
+    Configure,
+    /// A request received via the outbound control channel.
+    #[serde(rename = "controlChannel")]
+    ControlChannel,
+    /// A change synchronized from the portal.
+    #[serde(rename = "portal")]
+    Portal,
+    /// An action taken using the local command line interface.
+    #[serde(rename = "cli")]
+    Cli,
+    /// An action taken automatically by the daemon, such as a rollback or drift correction.
+    #[serde(rename = "scheduled")]
+    Scheduled,
+}
+
+/// An entry of the audit log.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "AuditEntry")]
+pub struct Entry {
+    time: SystemTime,
+    kind: Kind,
+    remote: Option<IpAddr>,
+    payload: Value,
+    status: u16,
+}
+
+/// Filter to select entries of the audit log.
+///
+/// Times are given in seconds since the UNIX epoch.
+#[derive(Clone, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Filter {
+    #[serde(default)]
+    since: Option<u64>,
+    #[serde(default)]
+    until: Option<u64>,
+    #[serde(default)]
+    kind: Option<Kind>,
+    /// Return at most this many of the latest matching entries.
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+impl Filter {
+    fn matches(&self, entry: &Entry) -> bool {
+        let secs = entry
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        self.since.is_none_or(|since| secs >= since)
+            && self.until.is_none_or(|until| secs <= until)
+            && self.kind.is_none_or(|kind| kind == entry.kind)
+    }
+}
+
+/// Records a control action in the audit log.
+///
+/// The `remote` is the peer address of the connection, since headers such as `X-Real-IP`
+/// can be forged by any caller.
+///
+/// Errors are logged, since a failure to audit must not fail the action itself.
+pub fn record<T>(kind: Kind, remote: Option<SocketAddr>, payload: &T, status: Status)
+where
+    T: Serialize + ?Sized,
+{
+    let entry = Entry {
+        time: SystemTime::now(),
+        kind,
+        remote: remote.map(|remote| remote.ip()),
+        payload: serde_json::to_value(payload).unwrap_or(Value::Null),
+        status: status.code,
+    };
+
+    if let Err(error) = append(&entry) {
+        error!("Could not write audit log: {error}");
+    }
+}
+
+/// Returns the status to audit for the given result of an action.
+pub const fn status_of<T, E>(result: &Result<T, E>) -> Status {
+    if result.is_ok() {
+        Status::Ok
+    } else {
+        Status::BadRequest
+    }
+}
+
+/// Returns the entries of the audit log matching the given filter, oldest first.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] if the audit log could not be read.
+pub fn query(filter: &Filter) -> std::io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for filename in (0..=MAX_ROTATED_FILES).rev().map(filename) {
+        let text = match read_to_string(filename) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+
+        entries.extend(
+            text.lines()
+                .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+                .filter(|entry| filter.matches(entry)),
+        );
+    }
+
+    if let Some(limit) = filter.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+
+    Ok(entries)
+}
+
+fn append(entry: &Entry) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(entry)
+        .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error))?;
+    line.push('\n');
+    let _guard = LOCK.lock();
+    let filename = filename(0);
+
+    if let Some(parent) = filename.parent() {
+        create_dir_all(parent)?;
+    }
+
+    if metadata(&filename).is_ok_and(|metadata| metadata.len() >= MAX_FILE_SIZE) {
+        rotate()?;
+    }
+
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(filename)?
+        .write_all(line.as_bytes())
+}
+
+/// Shifts the audit logs by one, discarding the oldest one.
+fn rotate() -> std::io::Result<()> {
+    for index in (0..MAX_ROTATED_FILES).rev() {
+        match rename(filename(index), filename(index + 1)) {
+            Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the path to the current (`0`) or a rotated audit log.
+fn filename(index: usize) -> PathBuf {
+    if index == 0 {
+        state::path(AUDIT_LOG)
+    } else {
+        state::path(&format!("{AUDIT_LOG}.{index}"))
+    }
+}
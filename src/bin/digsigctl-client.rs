@@ -0,0 +1,275 @@
+//! Client to control many digital signage systems at once.
+//!
+//! This program runs commands against the RPC servers of the given systems concurrently.
+use clap::{Parser, Subcommand, ValueEnum};
+use digsigctl::client::{Client, DEFAULT_PORT};
+use digsigctl::{Command, Config, OperationMode, Request};
+use futures_util::stream::{self, StreamExt};
+use rocket::serde::json::{serde_json, Value};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(about, author, version)]
+struct Args {
+    /// Host names or IP addresses of the systems.
+    #[clap(short = 'H', long = "host")]
+    hosts: Vec<String>,
+
+    /// File containing one host per line.
+    #[clap(short = 'f', long)]
+    hosts_file: Option<PathBuf>,
+
+    #[clap(short, long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// Timeout in seconds per request.
+    #[clap(short, long, default_value_t = 30)]
+    timeout: u64,
+
+    /// Maximum amount of systems to contact at the same time.
+    #[clap(short, long, default_value_t = 16)]
+    concurrency: usize,
+
+    #[clap(short, long, value_enum, default_value_t = Format::Table)]
+    output: Format,
+
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Run an RPC request given as JSON, e.g. '"identify"' or '{"reboot": 10}'.
+    Rpc { request: String },
+    /// Beep the PC speaker.
+    Beep,
+    /// Beep and display the hostname on the screen.
+    Identify,
+    /// Reboot the systems.
+    Reboot {
+        /// Delay in seconds.
+        delay: Option<u64>,
+    },
+    /// Restart the web browser.
+    RestartBrowser,
+    /// Get or set the operation mode.
+    OperationMode {
+        /// The operation mode to set, e.g. "chromium".
+        mode: Option<String>,
+    },
+    /// Configure the startup URL.
+    Configure {
+        url: String,
+
+        /// Roll back, unless confirmed within this amount of seconds.
+        #[clap(long)]
+        confirm_timeout: Option<u64>,
+    },
+    /// Show the system information.
+    Sysinfo {
+        /// Use the versioned format.
+        #[clap(long)]
+        v2: bool,
+    },
+    /// Show the capabilities.
+    Capabilities,
+    /// Download screenshots to a directory.
+    Screenshot { directory: PathBuf },
+}
+
+/// Result of an action on a host.
+struct Outcome {
+    host: String,
+    result: anyhow::Result<Value>,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let hosts = hosts(&args).unwrap_or_else(|error| {
+        eprintln!("Could not read hosts file: {error}");
+        exit(1);
+    });
+
+    if hosts.is_empty() {
+        eprintln!("No hosts given.");
+        exit(1);
+    }
+
+    if let Action::Screenshot { directory } = &args.action {
+        if let Err(error) = create_dir_all(directory) {
+            eprintln!("Could not create directory: {error}");
+            exit(1);
+        }
+    }
+
+    let timeout = Duration::from_secs(args.timeout);
+    let mut outcomes: Vec<Outcome> = stream::iter(hosts)
+        .map(|host| async {
+            let result = run(&host, args.port, timeout, &args.action).await;
+            Outcome { host, result }
+        })
+        .buffer_unordered(args.concurrency.max(1))
+        .collect()
+        .await;
+    outcomes.sort_by(|lhs, rhs| lhs.host.cmp(&rhs.host));
+
+    match args.output {
+        Format::Table => print_table(&outcomes),
+        Format::Json => print_json(&outcomes),
+        Format::Csv => print_csv(&outcomes),
+    }
+
+    if outcomes.iter().any(|outcome| outcome.result.is_err()) {
+        exit(2);
+    }
+}
+
+fn hosts(args: &Args) -> std::io::Result<Vec<String>> {
+    let mut hosts = args.hosts.clone();
+
+    if let Some(file) = &args.hosts_file {
+        hosts.extend(
+            read_to_string(file)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    hosts.sort();
+    hosts.dedup();
+    Ok(hosts)
+}
+
+async fn run(host: &str, port: u16, timeout: Duration, action: &Action) -> anyhow::Result<Value> {
+    let client = Client::new(host, port, timeout)?;
+
+    match action {
+        Action::Rpc { request } => client.rpc(&serde_json::from_str(request)?).await,
+        Action::Beep => client.rpc(&Request::Single(Command::Beep)).await,
+        Action::Identify => client.rpc(&Request::Single(Command::Identify)).await,
+        Action::Reboot { delay } => client.rpc(&Request::Single(Command::Reboot(*delay))).await,
+        Action::RestartBrowser => {
+            client
+                .rpc(&Request::Single(Command::RestartWebBrowser))
+                .await
+        }
+        Action::OperationMode { mode } => {
+            let mode = mode
+                .as_ref()
+                .map(|mode| serde_json::from_value::<OperationMode>(Value::String(mode.clone())))
+                .transpose()?;
+            client
+                .rpc(&Request::Single(Command::OperationMode(mode)))
+                .await
+        }
+        Action::Configure {
+            url,
+            confirm_timeout,
+        } => {
+            let config = Config::new(url.clone()).with_confirm_timeout(*confirm_timeout);
+            client.configure(&config).await.map(Value::String)
+        }
+        Action::Sysinfo { v2 } => {
+            if *v2 {
+                client.sysinfo_v2().await
+            } else {
+                client.sysinfo().await
+            }
+        }
+        Action::Capabilities => client.capabilities().await,
+        Action::Screenshot { directory } => {
+            let file = screenshot_file(directory, host);
+            write(&file, client.screenshot().await?)?;
+            Ok(Value::String(file.display().to_string()))
+        }
+    }
+}
+
+fn screenshot_file(directory: &Path, host: &str) -> PathBuf {
+    directory.join(format!("{}.png", host.replace([':', '/'], "_")))
+}
+
+/// Returns the result as a single line of text.
+fn summary(result: &anyhow::Result<Value>) -> String {
+    match result {
+        Ok(Value::String(text)) => text.trim().to_string(),
+        Ok(value) => value.to_string(),
+        Err(error) => error.to_string(),
+    }
+}
+
+fn print_table(outcomes: &[Outcome]) {
+    let width = outcomes
+        .iter()
+        .map(|outcome| outcome.host.len())
+        .max()
+        .unwrap_or_default()
+        .max("HOST".len());
+    println!("{:width$}  STATUS  RESULT", "HOST");
+
+    for outcome in outcomes {
+        println!(
+            "{:width$}  {:6}  {}",
+            outcome.host,
+            if outcome.result.is_ok() {
+                "ok"
+            } else {
+                "error"
+            },
+            summary(&outcome.result)
+        );
+    }
+}
+
+fn print_json(outcomes: &[Outcome]) {
+    let outcomes: Vec<Value> = outcomes
+        .iter()
+        .map(|outcome| match &outcome.result {
+            Ok(result) => serde_json::json!({"host": outcome.host, "ok": true, "result": result}),
+            Err(error) => {
+                serde_json::json!({"host": outcome.host, "ok": false, "error": error.to_string()})
+            }
+        })
+        .collect();
+
+    match serde_json::to_string_pretty(&outcomes) {
+        Ok(json) => println!("{json}"),
+        Err(error) => eprintln!("Could not serialize results: {error}"),
+    }
+}
+
+fn print_csv(outcomes: &[Outcome]) {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    let result = writer
+        .write_record(["host", "ok", "result"])
+        .and_then(|()| {
+            outcomes.iter().try_for_each(|outcome| {
+                writer.write_record([
+                    outcome.host.as_str(),
+                    if outcome.result.is_ok() {
+                        "true"
+                    } else {
+                        "false"
+                    },
+                    summary(&outcome.result).as_str(),
+                ])
+            })
+        });
+
+    if let Err(error) = result.and_then(|()| writer.flush().map_err(Into::into)) {
+        eprintln!("Could not write CSV: {error}");
+    }
+}
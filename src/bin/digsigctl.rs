@@ -1,46 +1,312 @@
-    //! Digital signage system controller.
+//! Digital signage system controller.
 //!
 //! This program is intended to run on digital signage systems and act as an RPC server.
+//! Technicians on site can also run its commands locally, without going through the server.
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use digsigctl::audit::{self, status_of, Kind};
+use digsigctl::constants::CONFIGURATION_SERVICE;
+use digsigctl::history::Source;
+use digsigctl::jobs;
+use digsigctl::logging::{self, RequestId};
+use digsigctl::openapi::openapi;
 use digsigctl::{
-    discover_address_or_exit, take_screenshot, Command, Config, is_active,
-    Result, ScreenshotResponse, SystemInformation, apply_portal_config_if_needed, verify_startup_page,
+    apply_portal_config_if_needed, is_active, serve_on_addresses, set_networks,
+    spawn_control_channel, spawn_heartbeat, spawn_reconciler, take_screenshot, verify_startup_page,
+    watch_addresses, Capabilities, Command, Config, Network, OperationMode, Request, Result,
+    ScreenshotResponse, Stopped, SystemInformation, SystemInformationV2, DEFAULT_NETWORK,
 };
-use rocket::serde::json::Json;
-use rocket::{get, launch, post, routes, Build, Rocket};
+use rocket::error::ErrorKind;
+use rocket::serde::json::{serde_json, Json, Value};
+use rocket::{delete, get, post, routes, Build, Rocket};
+use std::fs::write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::process::exit;
 use std::thread;
+use std::time::Duration;
 use subprocess::ExitStatus;
 use tokio::runtime::Runtime;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, info_span};
 
-const CONFIGURATION_MODE: &str = "hidslcfg.service";
+/// Time to wait before retrying to bind to an address, e.g. while IPv6 duplicate address detection is running.
+const BIND_RETRY_DELAY: Duration = Duration::from_secs(5);
 
 #[derive(Parser)]
 #[clap(about, author, version)]
 struct Args {
-    #[clap(short, long, default_value = "fd56:1dda:8794:cb90::/64")]
-    network: String,
+    /// IP networks or interface names to listen in, in order of preference.
+    ///
+    /// The server listens on every address found within these networks and re-binds whenever
+    /// they change. Template values like `{ip}` resolve to the first address found.
+    #[clap(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = DEFAULT_NETWORK
+    )]
+    network: Vec<Network>,
 
     #[clap(short, long, default_value_t = 5000)]
     port: u16,
+
+    /// Interval in seconds in which to correct drift from the desired state.
+    #[clap(long, default_value_t = 60)]
+    reconcile_interval: u64,
+
+    /// Log filter directives, e.g. `info,digsigctl::reconcile=debug`.
+    ///
+    /// The environment variable `RUST_LOG` takes precedence, if set.
+    #[clap(long, global = true, default_value = logging::DEFAULT_FILTER)]
+    log_filter: String,
+
+    /// Print results of local commands as JSON instead of human-readable text.
+    #[clap(long, global = true)]
+    json: bool,
+
+    /// Runs the RPC server, if omitted.
+    #[clap(subcommand)]
+    action: Option<Action>,
 }
 
-#[launch]
-fn rocket() -> Rocket<Build> {
+#[derive(Subcommand)]
+enum Action {
+    /// Run the RPC server.
+    Serve,
+    /// Show the system information.
+    Sysinfo {
+        /// Use the versioned format.
+        #[clap(long)]
+        v2: bool,
+    },
+    /// Show the capabilities.
+    Capabilities,
+    /// Get or set the operation mode.
+    Mode {
+        /// The operation mode to set, e.g. "chromium".
+        #[clap(value_parser = parse_operation_mode)]
+        mode: Option<OperationMode>,
+    },
+    /// Configure the startup URL.
+    ///
+    /// The configuration is applied permanently, since a confirmation timeout
+    /// requires the RPC server to roll back unconfirmed configurations.
+    Configure { url: String },
+    /// Beep the PC speaker.
+    Beep,
+    /// Beep and display the hostname on the screen.
+    Identify,
+    /// Reboot the system.
+    Reboot {
+        /// Delay in seconds.
+        delay: Option<u64>,
+    },
+    /// Restart the web browser.
+    RestartBrowser,
+    /// Save a screenshot as PNG image.
+    Screenshot { file: PathBuf },
+}
+
+fn main() {
     let args = Args::parse();
+    logging::init(&args.log_filter);
+    set_networks(args.network.clone());
+
+    match &args.action {
+        None | Some(Action::Serve) => {
+            if let Err(error) = rocket::execute(serve(&args)) {
+                error!("{}", error.pretty_print());
+                exit(1);
+            }
+        }
+        Some(action) => exit(run_locally(action, args.json)),
+    }
+}
+
+/// Runs the given action locally and returns the exit code.
+fn run_locally(action: &Action, json: bool) -> i32 {
+    let result = match action {
+        Action::Serve => unreachable!("the server is not run locally"),
+        Action::Sysinfo { v2 } => {
+            if *v2 {
+                Result::Success(Box::new(SystemInformationV2::default()))
+            } else {
+                Result::Success(Box::new(SystemInformation::default()))
+            }
+        }
+        Action::Capabilities => Result::Success(Box::new(Capabilities::default())),
+        Action::Mode { mode } => run_command(&Command::OperationMode(mode.clone())),
+        Action::Configure { url } => {
+            let config = Config::new(url.clone());
+            let result = config.submit(Source::Cli);
+            audit::record(Kind::Cli, None, &config, status_of(&result));
+            result.map_or_else(
+                |error| Result::Error(error.to_string().into()),
+                |message| Result::Success(Box::new(message)),
+            )
+        }
+        Action::Beep => run_command(&Command::Beep),
+        Action::Identify => run_command(&Command::Identify),
+        Action::Reboot { delay } => reboot(*delay),
+        Action::RestartBrowser => run_command(&Command::RestartWebBrowser),
+        Action::Screenshot { file } => take_screenshot()
+            .and_then(|image| write(file, image).map_err(Into::into))
+            .map_or_else(
+                |error| Result::Error(error.to_string().into()),
+                |()| Result::Success(Box::new(format!("Screenshot saved to {}.", file.display()))),
+            ),
+    };
+
+    match result.to_value() {
+        Ok(value) if json => match serde_json::to_string_pretty(&value) {
+            Ok(text) if matches!(result, Result::Success(_)) => println!("{text}"),
+            Ok(text) => eprintln!("{text}"),
+            Err(error) => eprintln!("Could not serialize result: {error}"),
+        },
+        Ok(value) => {
+            let text = human_readable(&value, 0);
+            if matches!(result, Result::Success(_)) {
+                print!("{text}");
+            } else {
+                eprint!("{text}");
+            }
+        }
+        Err(error) => eprintln!("Could not serialize result: {error}"),
+    }
+
+    i32::from(!matches!(result, Result::Success(_)))
+}
+
+/// Reboots the system after the given delay in seconds.
+///
+/// Unlike the RPC command, this blocks, since the process would otherwise exit before rebooting.
+fn reboot(delay: Option<u64>) -> Result {
+    if let Some(delay) = delay {
+        info!("Rebooting system in {delay} seconds");
+        thread::sleep(Duration::from_secs(delay));
+    }
+
+    let result = system_shutdown::reboot().map_or_else(
+        |error| Result::Error(format!("Could not reboot: {error}").into()),
+        |()| Result::Success(Box::new("Rebooting system.")),
+    );
+    audit::record(Kind::Cli, None, &Command::Reboot(delay), result.status());
+    result
+}
+
+fn run_command(command: &Command) -> Result {
+    let result = command.run_as(Source::Cli);
+    audit::record(Kind::Cli, None, command, result.status());
+    result
+}
+
+fn parse_operation_mode(mode: &str) -> serde_json::Result<OperationMode> {
+    serde_json::from_value(Value::String(mode.to_string()))
+}
+
+/// Renders a JSON value as indented lines of text.
+fn human_readable(value: &Value, indent: usize) -> String {
+    let scalar = |value: &Value| match value {
+        Value::Null => Some("-".to_string()),
+        Value::String(text) => Some(text.clone()),
+        Value::Array(items) if items.is_empty() => Some("-".to_string()),
+        Value::Object(object) if object.is_empty() => Some("-".to_string()),
+        Value::Array(_) | Value::Object(_) => None,
+        other => Some(other.to_string()),
+    };
+    let pad = " ".repeat(indent);
+
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| {
+                scalar(value).map_or_else(
+                    || format!("{pad}{key}:\n{}", human_readable(value, indent + 2)),
+                    |text| format!("{pad}{key}: {text}\n"),
+                )
+            })
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                scalar(item).map_or_else(
+                    || format!("{pad}-\n{}", human_readable(item, indent + 2)),
+                    |text| format!("{pad}- {text}\n"),
+                )
+            })
+            .collect(),
+        other => scalar(other)
+            .map(|text| format!("{pad}{text}\n"))
+            .unwrap_or_default(),
+    }
+}
+
+/// Runs the RPC server on the addresses discovered in the configured networks.
+///
+/// A server is running on each such address and all are restarted whenever the addresses change.
+async fn serve(args: &Args) -> std::result::Result<(), rocket::Error> {
+    spawn_background_tasks(args);
 
+    serve_on_addresses(
+        watch_addresses(args.network.clone()),
+        BIND_RETRY_DELAY,
+        shutdown_requested(),
+        |address, mut addresses| async move {
+            let rocket = rocket(args, address).ignite().await?;
+            let shutdown = rocket.shutdown();
+            let rebind = tokio::spawn(async move {
+                if addresses.changed().await.is_ok() {
+                    info!("Addresses changed, shutting down server on {address}");
+                    shutdown.notify();
+                }
+            });
+            let result = rocket.launch().await;
+            rebind.abort();
+
+            match result {
+                Ok(_) => Ok(Stopped::ShutDown),
+                Err(error) if matches!(error.kind(), ErrorKind::Bind(_)) => {
+                    Ok(Stopped::BindFailed(error))
+                }
+                Err(error) => Err(error),
+            }
+        },
+    )
+    .await
+}
+
+/// Waits for a signal to shut down, as handled by the server while it is running.
+async fn shutdown_requested() {
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(error) => {
+            error!("Could not listen for SIGTERM: {error}");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+fn spawn_background_tasks(args: &Args) {
     // Run portal verification on startup in a separate thread
     // Only apply configuration if the portal URL doesn't match the current startup page
-    // Skip this if operation mode is CONFIGURATION_MODE
+    // Skip this if operation mode is CONFIGURATION_SERVICE
     thread::spawn(|| {
-        // Check if CONFIGURATION_MODE is active
-        let is_config_mode = match is_active(CONFIGURATION_MODE) {
+        let _span = info_span!("portal_verification").entered();
+
+        // Check if CONFIGURATION_SERVICE is active
+        let is_config_mode = match is_active(CONFIGURATION_SERVICE) {
             Ok(ExitStatus::Exited(0)) => true,
             _ => false,
         };
 
         if is_config_mode {
-            eprintln!("Skipping portal verification - system is in configuration mode");
+            info!("Skipping portal verification - system is in configuration mode");
             return;
         }
 
@@ -53,42 +319,77 @@ fn rocket() -> Rocket<Build> {
                         match apply_portal_config_if_needed().await {
                             Ok(applied) => {
                                 if applied {
-                                    eprintln!("Portal configuration applied on startup - URL mismatch detected");
+                                    info!("Portal configuration applied on startup - URL mismatch detected");
                                 } else {
-                                    eprintln!("Portal configuration not needed - URL already matches");
+                                    info!("Portal configuration not needed - URL already matches");
                                 }
                             }
                             Err(e) => {
-                                eprintln!("Failed to apply portal config on startup: {}", e);
+                                error!("Failed to apply portal config on startup: {}", e);
                             }
                         }
                     } else {
-                        eprintln!("Portal configuration not needed - URL already matches");
+                        info!("Portal configuration not needed - URL already matches");
                     }
                 }
                 Err(e) => {
-                    eprintln!("Failed to verify startup page on startup: {}", e);
+                    error!("Failed to verify startup page on startup: {}", e);
                 }
             }
         });
     });
 
+    spawn_reconciler(Duration::from_secs(args.reconcile_interval));
+    spawn_heartbeat();
+    spawn_control_channel();
+}
+
+fn rocket(args: &Args, address: IpAddr) -> Rocket<Build> {
     #[allow(clippy::redundant_type_annotations)]
     rocket::custom(
         rocket::Config::figment()
             .merge(("port", args.port))
-            .merge(("address", discover_address_or_exit(args.network.as_str()))),
+            .merge(("address", address)),
+    )
+    .mount(
+        "/",
+        routes![
+            configure,
+            configure_v2,
+            screenshot,
+            sysinfo,
+            sysinfo_v2,
+            capabilities,
+            openapi_json,
+            rpc,
+            list_jobs,
+            get_job,
+            cancel_job,
+            verify_portal,
+            get_portal_url
+        ],
     )
-    .mount("/", routes![configure, screenshot, sysinfo, rpc, verify_portal, get_portal_url])
 }
 
 #[allow(clippy::needless_pass_by_value)]
 #[post("/configure", format = "application/json", data = "<config>")]
-fn configure(config: Json<Config>) -> String {
-    match config.apply() {
-        Ok(()) => "Configuration applied.".to_string(),
-        Err(error) => error.to_string(),
-    }
+fn configure(config: Json<Config>, remote: Option<SocketAddr>, request_id: RequestId) -> String {
+    let _span = info_span!("request", request_id = %request_id, command = "configure").entered();
+    let result = config.submit(Source::Rpc);
+    audit::record(Kind::Configure, remote, &*config, status_of(&result));
+    result.unwrap_or_else(|error| error.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/v2/configure", format = "application/json", data = "<config>")]
+fn configure_v2(config: Json<Config>, remote: Option<SocketAddr>, request_id: RequestId) -> Result {
+    let _span = info_span!("request", request_id = %request_id, command = "configure").entered();
+    let result = config.submit(Source::Rpc);
+    audit::record(Kind::Configure, remote, &*config, status_of(&result));
+    result.map_or_else(
+        |error| Result::Error(error.to_string().into()),
+        |message| Result::Success(Box::new(message)),
+    )
 }
 
 #[get("/screenshot")]
@@ -101,10 +402,59 @@ fn sysinfo() -> Json<SystemInformation> {
     Json(SystemInformation::default())
 }
 
+#[get("/v2/sysinfo", format = "application/json")]
+fn sysinfo_v2() -> Json<SystemInformationV2> {
+    Json(SystemInformationV2::default())
+}
+
+#[get("/capabilities", format = "application/json")]
+fn capabilities() -> Json<Capabilities> {
+    Json(Capabilities::default())
+}
+
+#[get("/openapi.json", format = "application/json")]
+fn openapi_json() -> Json<Value> {
+    Json(openapi())
+}
+
 #[allow(clippy::needless_pass_by_value)]
-#[post("/rpc", format = "application/json", data = "<command>")]
-fn rpc(command: Json<Command>) -> Result {
-    command.run()
+#[post("/rpc?<background>", format = "application/json", data = "<command>")]
+fn rpc(
+    command: Json<Request>,
+    background: Option<bool>,
+    remote: Option<SocketAddr>,
+    request_id: RequestId,
+) -> Result {
+    let _span = info_span!("request", request_id = %request_id, command = command.name()).entered();
+    let result = if background.unwrap_or(false) || command.requires_background() {
+        jobs::submit((*command).clone(), Kind::Rpc, remote)
+    } else {
+        command.run()
+    };
+    audit::record(Kind::Rpc, remote, &*command, result.status());
+    result
+}
+
+#[get("/jobs")]
+fn list_jobs() -> Result {
+    Result::Success(Box::new(jobs::list()))
+}
+
+#[get("/jobs/<id>")]
+fn get_job(id: u64) -> Result {
+    jobs::get(id)
+}
+
+#[delete("/jobs/<id>")]
+fn cancel_job(id: u64, remote: Option<SocketAddr>) -> Result {
+    let result = jobs::cancel(id);
+    audit::record(
+        Kind::Rpc,
+        remote,
+        &format!("cancelJob {id}"),
+        result.status(),
+    );
+    result
 }
 
 /// Verify if the portal URL matches the current Chromium startup page
@@ -126,12 +476,10 @@ async fn verify_portal() -> String {
 #[get("/portal-url")]
 async fn get_portal_url() -> String {
     match digsigctl::portal::get_hostname() {
-        Ok(hostname) => {
-            match digsigctl::portal::fetch_portal_url(&hostname).await {
-                Ok(url) => url,
-                Err(e) => format!("Error fetching portal URL: {}", e),
-            }
-        }
+        Ok(hostname) => match digsigctl::portal::fetch_portal_url(&hostname).await {
+            Ok(url) => url,
+            Err(e) => format!("Error fetching portal URL: {}", e),
+        },
         Err(e) => format!("Error getting hostname: {}", e),
     }
 }
@@ -0,0 +1,75 @@
+use crate::rpc::operation_mode::OperationMode;
+use crate::rpc::Command;
+use crate::screenshot;
+use crate::sysinfo::application::Metadata;
+use crate::sysinfo::sensors::SENSORS;
+use crate::sysinfo::smart::SMARTCTL;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::env::{split_paths, var_os};
+use std::path::Path;
+
+const XMESSAGE: &str = "xmessage";
+/// Input device of the PC speaker, as used for beeping.
+const PC_SPEAKER: &str = "/dev/input/by-path/platform-pcspkr-event-spkr";
+
+/// Capabilities of the system and of this daemon.
+///
+/// This allows the CMS to determine which features a system supports.
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct Capabilities {
+    version: &'static str,
+    commands: &'static [&'static str],
+    #[serde(rename = "operationModes")]
+    operation_modes: &'static [OperationMode],
+    applications: Vec<Metadata>,
+    #[serde(rename = "preferredApplication")]
+    preferred_application: Option<&'static str>,
+    screenshot: bool,
+    tools: Tools,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            commands: &Command::NAMES,
+            operation_modes: &OperationMode::ALL,
+            applications: OperationMode::installed()
+                .into_iter()
+                .map(Metadata::from)
+                .collect(),
+            preferred_application: OperationMode::preferred()
+                .map(|operation_mode| Metadata::from(operation_mode).name()),
+            screenshot: screenshot::is_supported(),
+            tools: Tools::default(),
+        }
+    }
+}
+
+/// Availability of external tools used by this daemon.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct Tools {
+    smartctl: bool,
+    sensors: bool,
+    xmessage: bool,
+    #[serde(rename = "pcSpeaker")]
+    pc_speaker: bool,
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Self {
+            smartctl: Path::new(SMARTCTL).is_file(),
+            sensors: Path::new(SENSORS).is_file(),
+            xmessage: in_path(XMESSAGE),
+            pc_speaker: Path::new(PC_SPEAKER).exists(),
+        }
+    }
+}
+
+/// Determines whether the given program can be found in the `PATH`.
+fn in_path(program: &str) -> bool {
+    var_os("PATH").is_some_and(|path| split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
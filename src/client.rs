@@ -0,0 +1,154 @@
+//! Client for the RPC server of `digsigctl`.
+
+use crate::rpc::Request;
+use crate::Config;
+use anyhow::anyhow;
+use reqwest::StatusCode;
+use rocket::serde::json::Value;
+use std::net::Ipv6Addr;
+use std::time::Duration;
+
+/// Default port of the RPC server.
+pub const DEFAULT_PORT: u16 = 5000;
+
+/// Client to control a digital signage system via its RPC server.
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Client {
+    /// Creates a new client for the system with the given host name or IP address.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`reqwest::Error`] if the HTTP client cannot be initialized.
+    pub fn new(host: &str, port: u16, timeout: Duration) -> reqwest::Result<Self> {
+        Ok(Self {
+            http: reqwest::Client::builder().timeout(timeout).build()?,
+            base_url: if host.parse::<Ipv6Addr>().is_ok() {
+                format!("http://[{host}]:{port}")
+            } else {
+                format!("http://{host}:{port}")
+            },
+        })
+    }
+
+    /// Runs the given RPC request and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed or the server returned errors,
+    /// which includes batches of which some commands failed.
+    pub async fn rpc(&self, request: &Request) -> anyhow::Result<Value> {
+        let response = self
+            .http
+            .post(self.url("/rpc"))
+            .json(request)
+            .send()
+            .await?;
+        let status = response.status();
+        let result = response.json::<Value>().await?;
+
+        if status.is_success() && status != StatusCode::MULTI_STATUS {
+            Ok(result)
+        } else {
+            Err(anyhow!("{status}: {result}"))
+        }
+    }
+
+    /// Applies the given configuration and returns the server's message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed or the configuration was not applied.
+    /// Servers that do not support `/v2/configure` only report failures in their message.
+    pub async fn configure(&self, config: &Config) -> anyhow::Result<String> {
+        let response = self
+            .http
+            .post(self.url("/v2/configure"))
+            .json(config)
+            .send()
+            .await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_FOUND {
+            return Ok(self
+                .http
+                .post(self.url("/configure"))
+                .json(config)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?);
+        }
+
+        let result = response.json::<Value>().await?;
+
+        match result {
+            Value::String(message) if status.is_success() => Ok(message),
+            result => Err(anyhow!("{status}: {result}")),
+        }
+    }
+
+    /// Returns the system information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed.
+    pub async fn sysinfo(&self) -> anyhow::Result<Value> {
+        self.get_json("/sysinfo").await
+    }
+
+    /// Returns the system information in the versioned format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed.
+    pub async fn sysinfo_v2(&self) -> anyhow::Result<Value> {
+        self.get_json("/v2/sysinfo").await
+    }
+
+    /// Returns the capabilities of the system.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed.
+    pub async fn capabilities(&self) -> anyhow::Result<Value> {
+        self.get_json("/capabilities").await
+    }
+
+    /// Takes a screenshot and returns it as PNG image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the request failed.
+    pub async fn screenshot(&self) -> anyhow::Result<Vec<u8>> {
+        let response = self.http.get(self.url("/screenshot")).send().await?;
+        let status = response.status();
+
+        if status.is_success() {
+            Ok(response.bytes().await?.to_vec())
+        } else {
+            Err(anyhow!("{status}: {}", response.text().await?))
+        }
+    }
+
+    async fn get_json(&self, path: &str) -> anyhow::Result<Value> {
+        Ok(self
+            .http
+            .get(self.url(path))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+}
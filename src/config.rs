@@ -1,23 +1,43 @@
- mod chromium_preferences;
+mod chromium_preferences;
+pub mod confirmation;
 mod error;
+mod template;
+mod url_policy;
 
 pub use crate::config::chromium_preferences::ChromiumPreferences;
+use crate::history::{self, Change, Source};
 use crate::rpc::chromium;
 pub use error::Error;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::time::Duration;
+pub use url_policy::UrlPolicy;
 
 /// Configuration settings for the digital signage system.
-#[derive(Debug, Deserialize, Eq, PartialEq)]
+///
+/// The URL may contain placeholders that are substituted on application,
+/// see [`Config::expanded_url`].
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct Config {
     url: String,
+    /// Seconds within which the configuration must be confirmed, at least 20.
+    #[serde(
+        default,
+        rename = "confirmTimeout",
+        skip_serializing_if = "Option::is_none"
+    )]
+    confirm_timeout: Option<u64>,
 }
 
 impl Config {
     /// Creates a new configuration with the specified URL.
     #[must_use]
     pub fn new(url: String) -> Self {
-        Self { url }
+        Self {
+            url,
+            confirm_timeout: None,
+        }
     }
 
     /// Returns the URL that shall be presented in the web browser for digital signage.
@@ -26,29 +46,117 @@ impl Config {
         self.url.as_str()
     }
 
+    /// Sets the timeout in seconds within which the configuration must be confirmed.
+    #[must_use]
+    pub const fn with_confirm_timeout(mut self, confirm_timeout: Option<u64>) -> Self {
+        self.confirm_timeout = confirm_timeout;
+        self
+    }
+
+    /// Returns the timeout within which the configuration must be confirmed, if any.
+    ///
+    /// See [`Config::apply_confirmed`] for details.
+    #[must_use]
+    pub fn confirm_timeout(&self) -> Option<Duration> {
+        self.confirm_timeout.map(Duration::from_secs)
+    }
+
+    /// Returns the URL with all placeholders, such as `{hostname}`, substituted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::UnresolvedPlaceholder`] if a placeholder could not be substituted.
+    pub fn expanded_url(&self) -> Result<String, Error> {
+        template::expand(self.url.as_str())
+    }
+
     /// Applies the configuration to the system.
     ///
     /// # Errors
     ///
-    /// Returns an [`digsigctl::config::Error`] if the configuration could not be applied.
+    /// Returns an [`digsigctl::config::Error`] if the configuration could not be applied,
+    /// e.g. if the URL is not allowed by the active [`UrlPolicy`].
     pub fn apply(&self) -> Result<(), anyhow::Error> {
-        chromium::await_shutdown();
-        self.update_chromium_preferences()?;
+        let url = self.expanded_url()?;
+        UrlPolicy::load()?.check(url.as_str())?;
+        confirmation::supersede();
+        activate(url.as_str())
+    }
 
-        if chromium::start() {
-            return Ok(());
+    /// Applies the configuration to the system and rolls it back automatically,
+    /// unless it is confirmed within the given timeout.
+    ///
+    /// The configuration will also be rolled back if Chromium is not running
+    /// or the page cannot be loaded after applying it.
+    /// It is only recorded as requested by the given source, once it has been confirmed.
+    /// See [`confirmation::confirm`] and [`confirmation::outcome`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`digsigctl::config::Error`] if the configuration could not be applied,
+    /// e.g. if the timeout is shorter than [`confirmation::MIN_TIMEOUT`].
+    pub fn apply_confirmed(&self, timeout: Duration, source: Source) -> Result<(), anyhow::Error> {
+        if timeout < confirmation::MIN_TIMEOUT {
+            return Err(Error::ConfirmTimeoutTooShort(confirmation::MIN_TIMEOUT).into());
         }
 
-        Err(Error::SubprocessFailed.into())
+        let url = self.expanded_url()?;
+        UrlPolicy::load()?.check(url.as_str())?;
+        confirmation::apply(
+            self.clone().with_confirm_timeout(None),
+            url,
+            source,
+            timeout,
+        )
     }
 
-    fn update_chromium_preferences(&self) -> Result<(), Error> {
-        let filename =
-            chromium::default_preferences_file().ok_or(Error::DefaultPreferencesNotFound)?;
-        let mut preferences = ChromiumPreferences::load(&filename)?;
-        preferences.update_or_init_session(self.url.as_str())?;
-        preferences.update_or_init_profile()?;
-        preferences.update_or_init_sessions()?;
-        preferences.save(filename)
+    /// Applies the configuration as requested by the given source and records it in the history.
+    ///
+    /// If a confirmation timeout is set, the configuration is applied with
+    /// [`Config::apply_confirmed`] and only recorded once confirmed, else with [`Config::apply`].
+    /// Returns a message describing the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`digsigctl::config::Error`] if the configuration could not be applied.
+    pub fn submit(&self, source: Source) -> Result<String, anyhow::Error> {
+        if let Some(timeout) = self.confirm_timeout() {
+            self.apply_confirmed(timeout, source)?;
+            return Ok(format!(
+                "Configuration applied. Awaiting confirmation within {} seconds.",
+                timeout.as_secs()
+            ));
+        }
+
+        self.apply()?;
+        history::record(source, Change::Config(self.clone()));
+        Ok("Configuration applied.".to_string())
     }
 }
+
+/// Returns the startup URL currently configured in the Chromium preferences.
+pub fn current_url() -> Option<String> {
+    chromium::default_preferences_file()
+        .and_then(|filename| ChromiumPreferences::load(filename).ok())
+        .and_then(|preferences| preferences.startup_url().map(ToString::to_string))
+}
+
+fn activate(url: &str) -> Result<(), anyhow::Error> {
+    chromium::await_shutdown();
+    update_chromium_preferences(url)?;
+
+    if chromium::start() {
+        return Ok(());
+    }
+
+    Err(Error::SubprocessFailed.into())
+}
+
+fn update_chromium_preferences(url: &str) -> Result<(), Error> {
+    let filename = chromium::default_preferences_file().ok_or(Error::DefaultPreferencesNotFound)?;
+    let mut preferences = ChromiumPreferences::load(&filename)?;
+    preferences.update_or_init_session(url)?;
+    preferences.update_or_init_profile()?;
+    preferences.update_or_init_sessions()?;
+    preferences.save(filename)
+}
@@ -34,6 +34,14 @@ impl ChromiumPreferences {
             .write_all(serde_json::to_string(&self.0)?.as_bytes())?)
     }
 
+    /// Returns the first startup URL, if any
+    #[must_use]
+    pub fn startup_url(&self) -> Option<&str> {
+        self.0
+            .pointer("/session/startup_urls/0")
+            .and_then(Value::as_str)
+    }
+
     /// Updates the _session_ object or initializes it, if it is not present
     ///
     /// # Errors
@@ -0,0 +1,269 @@
+use super::error::Error;
+use super::{activate, current_url, Config};
+use crate::audit::{self, Kind};
+use crate::history::{self, Change, Source};
+use crate::rpc::chromium;
+use rocket::http::Status;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tracing::{error, info_span, warn};
+
+/// Time to give Chromium to start up before verifying the new configuration.
+const SETTLE_TIME: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Shortest confirmation timeout, which leaves time to verify the new configuration.
+pub const MIN_TIMEOUT: Duration = SETTLE_TIME.saturating_add(REQUEST_TIMEOUT);
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+static OUTCOME: Mutex<Option<Outcome>> = Mutex::new(None);
+
+/// Outcome of a configuration that was applied with a confirmation timeout.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "status")]
+pub enum Outcome {
+    /// The configuration is active and awaiting confirmation.
+    #[serde(rename = "pending")]
+    Pending {
+        url: String,
+        #[serde(rename = "previousUrl")]
+        previous_url: Option<String>,
+        #[serde(rename = "remainingSecs")]
+        remaining_secs: u64,
+    },
+    /// The configuration has been confirmed and is permanent.
+    #[serde(rename = "confirmed")]
+    Confirmed { url: String },
+    /// The configuration has been replaced by another configuration before being confirmed.
+    #[serde(rename = "superseded")]
+    Superseded { url: String },
+    /// The configuration has been rolled back to the previous URL.
+    #[serde(rename = "rolledBack")]
+    RolledBack {
+        url: String,
+        #[serde(rename = "previousUrl")]
+        previous_url: String,
+        reason: String,
+    },
+    /// The configuration should have been rolled back, but this failed.
+    #[serde(rename = "rollbackFailed")]
+    RollbackFailed {
+        url: String,
+        reason: String,
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Pending {
+    generation: u64,
+    config: Config,
+    source: Source,
+    url: String,
+    previous_url: Option<String>,
+    deadline: Instant,
+}
+
+impl From<&Pending> for Outcome {
+    fn from(pending: &Pending) -> Self {
+        Self::Pending {
+            url: pending.url.clone(),
+            previous_url: pending.previous_url.clone(),
+            remaining_secs: pending
+                .deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs(),
+        }
+    }
+}
+
+/// Activates the given URL of the configuration and rolls it back unless confirmed within the
+/// given timeout.
+///
+/// The previous URL is the URL of the last confirmed configuration.
+/// If activating the URL fails, the previous URL is restored immediately.
+/// The configuration is recorded in the history as requested by the given source once confirmed.
+///
+/// # Errors
+///
+/// Returns an [`anyhow::Error`] if the URL could not be activated,
+/// which also describes a failure to restore the previous URL.
+pub fn apply(config: Config, url: String, source: Source, timeout: Duration) -> anyhow::Result<()> {
+    let previous_url = take_pending().map_or_else(current_url, |pending| {
+        set_outcome(Outcome::Superseded { url: pending.url });
+        pending.previous_url
+    });
+
+    if let Err(error) = activate(url.as_str()) {
+        if let Some(previous_url) = &previous_url {
+            if let Err(rollback_error) = activate(previous_url) {
+                error!("Could not restore previous URL {previous_url}: {rollback_error}");
+                return Err(anyhow::anyhow!(
+                    "{error}; restoring the previous URL {previous_url} failed as well: {rollback_error}"
+                ));
+            }
+        }
+
+        return Err(error);
+    }
+
+    let pending = Pending {
+        generation: GENERATION.fetch_add(1, Ordering::SeqCst),
+        config,
+        source,
+        url,
+        previous_url,
+        deadline: Instant::now() + timeout,
+    };
+    set_outcome(Outcome::from(&pending));
+
+    if let Ok(mut current) = PENDING.lock() {
+        current.replace(pending.clone());
+    }
+
+    thread::spawn(move || {
+        let _span = info_span!("confirmation", url = pending.url).entered();
+        watch(&pending);
+    });
+    Ok(())
+}
+
+/// Confirms the pending configuration, making it permanent, and records it in the history.
+///
+/// # Errors
+///
+/// Returns an [`Error::NothingToConfirm`] if there is no pending configuration.
+pub fn confirm() -> Result<Outcome, Error> {
+    let pending = take_pending().ok_or(Error::NothingToConfirm)?;
+    history::record(pending.source, Change::Config(pending.config));
+    let outcome = Outcome::Confirmed { url: pending.url };
+    set_outcome(outcome.clone());
+    Ok(outcome)
+}
+
+/// Discards the pending configuration, if any, since it is being replaced.
+pub fn supersede() {
+    if let Some(pending) = take_pending() {
+        set_outcome(Outcome::Superseded { url: pending.url });
+    }
+}
+
+/// Returns the outcome of the latest configuration that was applied with a confirmation timeout.
+pub fn outcome() -> Option<Outcome> {
+    if let Some(pending) = PENDING.lock().ok().and_then(|pending| pending.clone()) {
+        return Some(Outcome::from(&pending));
+    }
+
+    OUTCOME.lock().ok().and_then(|outcome| outcome.clone())
+}
+
+fn watch(pending: &Pending) {
+    thread::sleep(SETTLE_TIME.min(pending.deadline.saturating_duration_since(Instant::now())));
+
+    if let Err(reason) = verify(pending.url.as_str()) {
+        return rollback(pending.generation, reason);
+    }
+
+    while Instant::now() < pending.deadline {
+        if !is_pending(pending.generation) {
+            return;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    rollback(
+        pending.generation,
+        "not confirmed within timeout".to_string(),
+    );
+}
+
+/// Verifies that Chromium is running and the page can be loaded.
+fn verify(url: &str) -> Result<(), String> {
+    if !chromium::is_running() {
+        return Err("Chromium is not running".to_string());
+    }
+
+    Runtime::new()
+        .map_err(|error| error.to_string())?
+        .block_on(async {
+            reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()
+        })
+        .map(drop)
+        .map_err(|error| format!("page could not be loaded: {error}"))
+}
+
+fn rollback(generation: u64, reason: String) {
+    let Some(pending) = PENDING
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take_if(|pending| pending.generation == generation))
+    else {
+        return;
+    };
+
+    let outcome = match pending.previous_url {
+        Some(previous_url) => match activate(previous_url.as_str()) {
+            Ok(()) => {
+                history::record(
+                    Source::Rollback,
+                    Change::Config(Config::new(previous_url.clone())),
+                );
+                Outcome::RolledBack {
+                    url: pending.url,
+                    previous_url,
+                    reason,
+                }
+            }
+            Err(error) => Outcome::RollbackFailed {
+                url: pending.url,
+                reason,
+                error: error.to_string(),
+            },
+        },
+        None => Outcome::RollbackFailed {
+            url: pending.url,
+            reason,
+            error: "no previous URL".to_string(),
+        },
+    };
+
+    let status = if matches!(outcome, Outcome::RolledBack { .. }) {
+        Status::Ok
+    } else {
+        Status::InternalServerError
+    };
+    warn!("Configuration was rolled back: {outcome:?}");
+    audit::record(Kind::Scheduled, None, &outcome, status);
+    set_outcome(outcome);
+}
+
+fn is_pending(generation: u64) -> bool {
+    PENDING.lock().is_ok_and(|pending| {
+        pending
+            .as_ref()
+            .is_some_and(|pending| pending.generation == generation)
+    })
+}
+
+fn take_pending() -> Option<Pending> {
+    PENDING.lock().ok().and_then(|mut pending| pending.take())
+}
+
+fn set_outcome(outcome: Outcome) {
+    if let Ok(mut current) = OUTCOME.lock() {
+        current.replace(outcome);
+    }
+}
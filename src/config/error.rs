@@ -1,24 +1,41 @@
 use rocket::serde::json::serde_json;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
+    ConfirmTimeoutTooShort(Duration),
     DefaultPreferencesNotFound,
+    InvalidPolicy(String),
     IoError(std::io::Error),
     NotAJsonObject(&'static str),
+    NothingToConfirm,
     SerdeError(serde_json::Error),
     SubprocessFailed,
+    UnresolvedPlaceholder(String),
+    UrlNotAllowed(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::ConfirmTimeoutTooShort(min) => write!(
+                f,
+                "Confirmation timeout must be at least {} seconds",
+                min.as_secs()
+            ),
             Self::DefaultPreferencesNotFound => write!(f, "Default preferences not found"),
+            Self::InvalidPolicy(message) => write!(f, "Invalid URL policy: {message}"),
             Self::IoError(error) => <std::io::Error as Display>::fmt(error, f),
             Self::NotAJsonObject(key) => write!(f, "not a JSON object: {key}"),
+            Self::NothingToConfirm => write!(f, "No configuration awaiting confirmation"),
             Self::SerdeError(error) => <serde_json::Error as Display>::fmt(error, f),
             Self::SubprocessFailed => write!(f, "Subprocess failed"),
+            Self::UnresolvedPlaceholder(placeholder) => {
+                write!(f, "Unresolved placeholder in URL: {placeholder}")
+            }
+            Self::UrlNotAllowed(url) => write!(f, "URL not allowed by policy: {url}"),
         }
     }
 }
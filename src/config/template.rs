@@ -0,0 +1,110 @@
+use super::error::Error;
+use crate::identity::machine_id;
+use crate::net::{local_address, local_interface, mac_address};
+use crate::portal::get_hostname;
+use crate::sudo::sudo;
+use crate::try_from_io::TryFromIo;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fs::read_to_string;
+
+const PRODUCT_SERIAL: &str = "/sys/class/dmi/id/product_serial";
+const CAT: &str = "/usr/bin/cat";
+/// Characters to percent-encode in substituted values, i.e. all but the unreserved ones.
+const RESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Substitutes the placeholders in the given URL with values of the local system.
+///
+/// Supported placeholders are `{hostname}`, `{machine_id}`, `{mac}`, `{ip}` and `{serial}`.
+/// The values are percent-encoded, so that they can be used in paths and query strings.
+///
+/// # Errors
+///
+/// Returns an [`Error::UnresolvedPlaceholder`] if any placeholder is unknown,
+/// unterminated or its value could not be determined.
+pub fn expand(url: &str) -> Result<String, Error> {
+    expand_with(url, resolve)
+}
+
+fn expand_with(url: &str, resolve: impl Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let mut expanded = String::with_capacity(url.len());
+    let mut rest = url;
+
+    while let Some((prefix, tail)) = rest.split_once('{') {
+        let (placeholder, suffix) = tail
+            .split_once('}')
+            .ok_or_else(|| Error::UnresolvedPlaceholder(tail.to_string()))?;
+        expanded.push_str(prefix);
+        let value = resolve(placeholder)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| Error::UnresolvedPlaceholder(placeholder.to_string()))?;
+        expanded.extend(utf8_percent_encode(&value, RESERVED));
+        rest = suffix;
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn resolve(placeholder: &str) -> Option<String> {
+    match placeholder {
+        "hostname" => get_hostname().ok(),
+        "machine_id" => machine_id().ok(),
+        "ip" => local_address().map(|address| address.to_string()),
+        "mac" => local_interface().and_then(|interface| mac_address(&interface).ok()),
+        "serial" => serial(),
+        _ => None,
+    }
+}
+
+/// Returns the serial number of the system.
+///
+/// The DMI serial is only readable by root, so we fall back to `sudo` if necessary.
+fn serial() -> Option<String> {
+    read_to_string(PRODUCT_SERIAL)
+        .or_else(|_| {
+            sudo(CAT)
+                .arg(PRODUCT_SERIAL)
+                .output()
+                .and_then(|output| String::try_from_io(output.stdout))
+        })
+        .ok()
+        .map(|serial| serial.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_with;
+
+    fn resolve(placeholder: &str) -> Option<String> {
+        match placeholder {
+            "hostname" => Some("1.1030020".to_string()),
+            "mac" => Some("00:11:22:33:44:55".to_string()),
+            "ip" => Some("fd56:1dda:8794:cb90::5".to_string()),
+            "serial" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_with() {
+        assert_eq!(
+            expand_with("https://example.com/?id={hostname}&mac={mac}", resolve).ok(),
+            Some("https://example.com/?id=1.1030020&mac=00%3A11%3A22%3A33%3A44%3A55".to_string())
+        );
+        assert_eq!(
+            expand_with("https://example.com/{ip}", resolve).ok(),
+            Some("https://example.com/fd56%3A1dda%3A8794%3Acb90%3A%3A5".to_string())
+        );
+        assert_eq!(
+            expand_with("https://example.com/", resolve).ok(),
+            Some("https://example.com/".to_string())
+        );
+        assert!(expand_with("https://example.com/{machine_id}", resolve).is_err());
+        assert!(expand_with("https://example.com/{serial}", resolve).is_err());
+        assert!(expand_with("https://example.com/{hostname", resolve).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use super::error::Error;
+use crate::settings;
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+const SECTION: &str = "url_policy";
+const CUSTOMER_SECTION_PREFIX: &str = "url_policy:";
+const DEFAULT_SCHEMES: [&str; 2] = ["https", "http"];
+
+/// Policy restricting the URLs that may be configured as startup page.
+///
+/// The policy is read from the `[url_policy]` section of the settings file:
+///
+/// ```ini
+/// [url_policy]
+/// schemes = https
+/// domains = homeinfo.de, *.homeinfo.de
+/// customer = 1030020
+///
+/// [url_policy:1030020]
+/// domains = *.example.com
+/// ```
+///
+/// If no domains are configured at all, any host is allowed.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct UrlPolicy {
+    schemes: Vec<String>,
+    domains: Vec<String>,
+    customer: Option<String>,
+    customer_domains: Vec<String>,
+}
+
+impl UrlPolicy {
+    /// Loads the active URL policy from the settings file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::InvalidPolicy`] if the settings file could not be parsed.
+    pub fn load() -> Result<Self, Error> {
+        let settings = settings::load().map_err(Error::InvalidPolicy)?;
+        let mut schemes = settings::list(&settings, SECTION, "schemes");
+
+        if schemes.is_empty() {
+            schemes = DEFAULT_SCHEMES.map(String::from).to_vec();
+        }
+
+        let customer = settings.get(SECTION, "customer");
+        let customer_domains = customer
+            .as_ref()
+            .map(|customer| {
+                settings::list(
+                    &settings,
+                    &format!("{CUSTOMER_SECTION_PREFIX}{customer}"),
+                    "domains",
+                )
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            schemes,
+            domains: settings::list(&settings, SECTION, "domains"),
+            customer,
+            customer_domains,
+        })
+    }
+
+    /// Checks whether the given URL may be configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::UrlNotAllowed`] if the URL violates the policy.
+    pub fn check(&self, url: &str) -> Result<(), Error> {
+        if self.allows(url) {
+            Ok(())
+        } else {
+            Err(Error::UrlNotAllowed(url.to_string()))
+        }
+    }
+
+    fn allows(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+
+        if !self
+            .schemes
+            .iter()
+            .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+        {
+            return false;
+        }
+
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        if self.domains.is_empty() && self.customer_domains.is_empty() {
+            return true;
+        }
+
+        self.domains
+            .iter()
+            .chain(self.customer_domains.iter())
+            .any(|pattern| matches_domain(pattern, host))
+    }
+}
+
+/// Matches a host name against a domain pattern.
+///
+/// A pattern of the form `*.example.com` matches any subdomain of `example.com`,
+/// but not `example.com` itself. Any other pattern must match the host exactly.
+fn matches_domain(pattern: &str, host: &str) -> bool {
+    pattern.strip_prefix("*.").map_or_else(
+        || pattern.eq_ignore_ascii_case(host),
+        |domain| {
+            host.len() > domain.len() + 1
+                && host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_domain, UrlPolicy};
+
+    fn policy(domains: &[&str]) -> UrlPolicy {
+        UrlPolicy {
+            schemes: vec!["https".to_string()],
+            domains: domains.iter().map(ToString::to_string).collect(),
+            customer: None,
+            customer_domains: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_matches_domain() {
+        assert!(matches_domain("homeinfo.de", "homeinfo.de"));
+        assert!(matches_domain("*.homeinfo.de", "portal.homeinfo.de"));
+        assert!(!matches_domain("*.homeinfo.de", "homeinfo.de"));
+        assert!(!matches_domain("*.homeinfo.de", "evilhomeinfo.de"));
+        assert!(!matches_domain("homeinfo.de", "portal.homeinfo.de"));
+    }
+
+    #[test]
+    fn test_allows() {
+        let policy = policy(&["*.homeinfo.de"]);
+
+        assert!(policy.allows("https://portal.homeinfo.de/display?id=1"));
+        assert!(!policy.allows("http://portal.homeinfo.de/"));
+        assert!(!policy.allows("https://example.com/"));
+        assert!(!policy.allows("file:///etc/passwd"));
+        assert!(!policy.allows("chrome://settings"));
+        assert!(!policy.allows("not a url"));
+    }
+}
@@ -1,8 +1,18 @@
 pub const CHROMIUM_SERVICE: &str = "chromium.service";
 pub const INSTALLATION_INSTRUCTIONS_SERVICE: &str = "installation-instructions.service";
 pub const UNCONFIGURED_WARNING_SERVICE: &str = "unconfigured-warning.service";
-pub const CONFLICTING_SERVICES: [&str; 3] = [
+/// Service of the HTML5 digital signage application.
+pub const HTML_SERVICE: &str = "html5ds.service";
+/// Service of the AIR digital signage application.
+pub const AIR_SERVICE: &str = "application.service";
+/// Service that is active while the system is being set up on-site.
+pub const CONFIGURATION_SERVICE: &str = "hidslcfg.service";
+/// Directory containing the systemd units installed by packages.
+pub const SERVICES_DIR: &str = "/usr/lib/systemd/system";
+pub const CONFLICTING_SERVICES: [&str; 5] = [
     CHROMIUM_SERVICE,
+    HTML_SERVICE,
+    AIR_SERVICE,
     INSTALLATION_INSTRUCTIONS_SERVICE,
     UNCONFIGURED_WARNING_SERVICE,
 ];
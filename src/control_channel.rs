@@ -0,0 +1,289 @@
+use crate::audit::{self, Kind};
+use crate::config::Config;
+use crate::history::Source;
+use crate::identity::{sign, Identity};
+use crate::jobs;
+use crate::rpc::{self, Result};
+use crate::settings;
+use futures_util::{SinkExt, StreamExt};
+use rocket::http::Status;
+use rocket::serde::json::{serde_json, Value};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio::task::spawn_blocking;
+use tokio::time::{interval, sleep};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, info_span, warn, Instrument};
+
+const SECTION: &str = "control_channel";
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_mins(1);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Time without any message from the controller after which the connection is considered dead.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+const HOSTNAME_HEADER: &str = "X-Digsigctl-Hostname";
+const MACHINE_ID_HEADER: &str = "X-Digsigctl-Machine-Id";
+const TIMESTAMP_HEADER: &str = "X-Digsigctl-Timestamp";
+const SIGNATURE_HEADER: &str = "X-Digsigctl-Signature";
+
+/// Settings of the control channel, read from the `[control_channel]` section of the settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ChannelSettings {
+    url: String,
+    key: Option<Vec<u8>>,
+}
+
+impl ChannelSettings {
+    /// Loads the control channel settings.
+    ///
+    /// Returns `None` if no URL is configured, i.e. the control channel is disabled.
+    fn load() -> std::result::Result<Option<Self>, String> {
+        let settings = settings::load()?;
+        let Some(url) = settings.get(SECTION, "url") else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            url,
+            key: settings::key(&settings, SECTION)?,
+        }))
+    }
+}
+
+/// A request received from the controller.
+///
+/// It carries the same payloads as the `/rpc` and `/configure` endpoints.
+#[derive(Debug, Deserialize)]
+struct Request {
+    id: Option<Value>,
+    #[serde(flatten)]
+    body: RequestBody,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+enum RequestBody {
+    #[serde(rename = "command")]
+    Command(rpc::Request),
+    #[serde(rename = "config")]
+    Config(Config),
+}
+
+impl RequestBody {
+    fn name(&self) -> String {
+        match self {
+            Self::Command(command) => command.name(),
+            Self::Config(_) => "config".to_string(),
+        }
+    }
+
+    fn handle(&self) -> Result {
+        let result = match self {
+            Self::Command(command) if command.requires_background() => {
+                jobs::submit(command.clone(), Kind::ControlChannel, None)
+            }
+            Self::Command(command) => command.run(),
+            Self::Config(config) => config.submit(Source::Rpc).map_or_else(
+                |error| Result::Error(error.to_string().into()),
+                |message| Result::Success(Box::new(message)),
+            ),
+        };
+        audit::record(Kind::ControlChannel, None, self, result.status());
+        result
+    }
+}
+
+/// A response to a request, containing the serialized [`Result`].
+#[derive(Debug, Serialize)]
+struct Response {
+    id: Option<Value>,
+    status: u16,
+    result: Value,
+}
+
+impl Response {
+    fn new(id: Option<Value>, result: Result) -> Self {
+        let (status, json): (Status, String) = result.into();
+        Self {
+            id,
+            status: status.code,
+            result: serde_json::from_str(&json).unwrap_or(Value::String(json)),
+        }
+    }
+}
+
+/// Spawns a thread that maintains an outbound control channel to the controller, if configured.
+///
+/// This allows managing systems, that are not reachable via the VPN.
+/// The controller sends the same commands and configurations as accepted by the RPC server
+/// over a WebSocket connection, which is re-established automatically if it is lost.
+pub fn spawn_control_channel() {
+    let settings = match ChannelSettings::load() {
+        Ok(Some(settings)) => settings,
+        Ok(None) => return,
+        Err(error) => {
+            error!("Invalid control channel settings: {error}");
+            return;
+        }
+    };
+
+    let _ = thread::spawn(move || match Runtime::new() {
+        Ok(runtime) => runtime.block_on(run(&settings).instrument(info_span!("control_channel"))),
+        Err(error) => error!("Could not start control channel runtime: {error}"),
+    });
+}
+
+async fn run(settings: &ChannelSettings) {
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        match connect(settings).await {
+            Ok(connection) => {
+                info!("Control channel connected to {}", settings.url);
+                backoff = MIN_BACKOFF;
+
+                if let Err(error) = serve(connection).await {
+                    warn!("Control channel disconnected: {error}");
+                }
+            }
+            Err(error) => warn!("Could not connect control channel: {error}"),
+        }
+
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+type Connection =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect(settings: &ChannelSettings) -> anyhow::Result<Connection> {
+    let identity = Identity::default();
+    let hostname = identity.hostname().unwrap_or_default();
+    let machine_id = identity.machine_id().unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+    let mut request = settings.url.as_str().into_client_request()?;
+    let headers = request.headers_mut();
+    headers.insert(HOSTNAME_HEADER, HeaderValue::from_str(hostname)?);
+    headers.insert(MACHINE_ID_HEADER, HeaderValue::from_str(machine_id)?);
+    headers.insert(TIMESTAMP_HEADER, HeaderValue::from_str(&timestamp)?);
+
+    if let Some(key) = &settings.key {
+        let signature = sign(key, &signed_payload(hostname, machine_id, &timestamp));
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&format!("sha256={signature}"))?,
+        );
+    }
+
+    Ok(connect_async(request).await?.0)
+}
+
+/// Returns the payload whose signature authenticates the connection.
+///
+/// All identity headers are covered by the signature, so that none of them can be altered.
+fn signed_payload(hostname: &str, machine_id: &str, timestamp: &str) -> Vec<u8> {
+    format!("{hostname}:{machine_id}:{timestamp}").into_bytes()
+}
+
+async fn serve(connection: Connection) -> anyhow::Result<()> {
+    let (mut sink, mut stream) = connection.split();
+    let (sender, mut receiver) = mpsc::channel::<String>(16);
+    let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                last_seen = Instant::now();
+
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        let sender = sender.clone();
+                        tokio::spawn(async move {
+                            if let Ok(Some(response)) = spawn_blocking(move || handle(&text)).await {
+                                let _ = sender.send(response).await;
+                            }
+                        });
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(error)) => return Err(error.into()),
+                }
+            }
+            Some(response) = receiver.recv() => sink.send(Message::Text(response)).await?,
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > HEARTBEAT_TIMEOUT {
+                    return Err(anyhow::anyhow!("heartbeat timed out"));
+                }
+
+                sink.send(Message::Ping(Vec::new())).await?;
+            }
+        }
+    }
+}
+
+/// Handles a request and returns the serialized response.
+fn handle(text: &str) -> Option<String> {
+    let response = match serde_json::from_str::<Request>(text) {
+        Ok(request) => {
+            let _span = info_span!(
+                "request",
+                request_id = request.id.as_ref().map(ToString::to_string),
+                command = request.body.name(),
+            )
+            .entered();
+            Response::new(request.id, request.body.handle())
+        }
+        Err(error) => Response::new(
+            None,
+            Result::Error(format!("Invalid request: {error}").into()),
+        ),
+    };
+
+    serde_json::to_string(&response)
+        .inspect_err(|error| error!("Could not serialize response: {error}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{signed_payload, Request, RequestBody};
+    use crate::rpc::{self, Command};
+    use rocket::serde::json::{serde_json, Value};
+
+    #[test]
+    fn test_parse_request() {
+        let request: Request =
+            serde_json::from_str(r#"{"id": 42, "command": {"reboot": 10}}"#).unwrap();
+        assert_eq!(request.id, Some(Value::from(42)));
+        assert!(matches!(
+            request.body,
+            RequestBody::Command(rpc::Request::Single(Command::Reboot(Some(10))))
+        ));
+
+        let request: Request =
+            serde_json::from_str(r#"{"config": {"url": "https://example.com/"}}"#).unwrap();
+        assert_eq!(request.id, None);
+        assert!(
+            matches!(request.body, RequestBody::Config(config) if config.url() == "https://example.com/")
+        );
+    }
+
+    #[test]
+    fn test_signed_payload() {
+        assert_eq!(
+            signed_payload("1234.56", "0123abcd", "1700000000"),
+            b"1234.56:0123abcd:1700000000".to_vec()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! History of configurations and operation modes applied to the system.
+
+use crate::config::Config;
+use crate::reconcile::DesiredState;
+use crate::rpc::operation_mode::OperationMode;
+use crate::state;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::error;
+
+const HISTORY_FILE: &str = "history.json";
+const MAX_ENTRIES: usize = 100;
+
+/// Serializes read-modify-write cycles of the history file.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Origin of a change to the system.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Source {
+    /// The change was requested via the RPC server.
+    #[serde(rename = "rpc")]
+    Rpc,
+    /// The change was synchronized from the portal.
+    #[serde(rename = "portal")]
+    Portal,
+    /// The change was made using the local command line interface.
+    #[serde(rename = "cli")]
+    Cli,
+    /// The change was made by automatically rolling back an unconfirmed configuration.
+    #[serde(rename = "rollback")]
+    Rollback,
+}
+
+/// A change applied to the system.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Change {
+    /// A configuration has been applied.
+    #[serde(rename = "config")]
+    Config(Config),
+    /// The operation mode has been set.
+    #[serde(rename = "operationMode")]
+    OperationMode(OperationMode),
+    /// The productive application has been chosen and activated.
+    #[serde(rename = "productiveApplication")]
+    ProductiveApplication(OperationMode),
+}
+
+impl Change {
+    /// Applies the change to the system again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`anyhow::Error`] if the change could not be applied.
+    pub fn apply(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Config(config) => config.apply(),
+            Self::OperationMode(operation_mode) | Self::ProductiveApplication(operation_mode) => {
+                if operation_mode.set() {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!("Could not set operation mode."))
+                }
+            }
+        }
+    }
+}
+
+/// An entry of the history.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "HistoryEntry")]
+pub struct Entry {
+    time: SystemTime,
+    source: Source,
+    change: Change,
+}
+
+impl Entry {
+    /// Returns the change recorded by this entry.
+    #[must_use]
+    pub const fn change(&self) -> &Change {
+        &self.change
+    }
+}
+
+/// Records a successfully applied change in the history.
+///
+/// The change also becomes the new desired state of the system.
+/// The history is bounded, so the oldest entries are discarded.
+/// Errors are logged, since a failure to record must not fail the change itself.
+pub fn record(source: Source, change: Change) {
+    DesiredState::update(&change);
+    let _guard = LOCK.lock();
+    let mut entries = entries().unwrap_or_else(|error| {
+        error!("Could not read history: {error}");
+        Vec::new()
+    });
+    entries.insert(
+        0,
+        Entry {
+            time: SystemTime::now(),
+            source,
+            change,
+        },
+    );
+    entries.truncate(MAX_ENTRIES);
+
+    if let Err(error) = state::save(HISTORY_FILE, &entries) {
+        error!("Could not write history: {error}");
+    }
+}
+
+/// Returns the recorded history, newest entry first.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] if the history could not be read.
+pub fn entries() -> std::io::Result<Vec<Entry>> {
+    state::load(HISTORY_FILE).map(Option::unwrap_or_default)
+}
+
+/// Re-applies the n-th entry of the history, with `0` being the newest entry.
+///
+/// The re-applied change is recorded as a new entry with the given source.
+///
+/// # Errors
+///
+/// Returns an [`anyhow::Error`] if the entry does not exist or could not be applied.
+pub fn revert(index: usize, source: Source) -> anyhow::Result<Change> {
+    let change = entries()?
+        .get(index)
+        .map(|entry| entry.change().clone())
+        .ok_or_else(|| anyhow::anyhow!("No such history entry: {index}"))?;
+    change.apply()?;
+    record(source, change.clone());
+    Ok(change)
+}
@@ -0,0 +1,70 @@
+use crate::portal::get_hostname;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::fs::read_to_string;
+
+const MACHINE_ID: &str = "/etc/machine-id";
+
+/// Identity of the local digital signage system.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Identity {
+    hostname: Option<String>,
+    #[serde(rename = "machineId")]
+    machine_id: Option<String>,
+}
+
+impl Identity {
+    /// Returns the hostname of the system, which is also its ID.
+    #[must_use]
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// Returns the systemd machine ID of the system.
+    #[must_use]
+    pub fn machine_id(&self) -> Option<&str> {
+        self.machine_id.as_deref()
+    }
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Self {
+            hostname: get_hostname().ok(),
+            machine_id: machine_id().ok(),
+        }
+    }
+}
+
+/// Returns the systemd machine ID of the system.
+///
+/// # Errors
+///
+/// This function will return an [`std::io::Error`] if `/etc/machine-id` could not be read.
+pub fn machine_id() -> std::io::Result<String> {
+    read_to_string(MACHINE_ID).map(|machine_id| machine_id.trim().to_string())
+}
+
+/// Signs the given payload with the device key, returning a hex-encoded HMAC-SHA256.
+#[must_use]
+pub fn sign(key: &[u8], payload: &[u8]) -> String {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    fn test_sign() {
+        // Test vector from RFC 4231, test case 2.
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Verification of the files of installed packages.
+//!
+//! This detects files that have been corrupted, e.g. by a power loss while writing to the
+//! root filesystem. The latest verification is stored, so that it can be reported in the
+//! system information.
+
+use crate::jobs::Context;
+use crate::pacman::verify_files;
+use crate::settings;
+use crate::state;
+use crate::timestamp::Timestamp;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+const VERIFICATION_FILE: &str = "integrity.json";
+const SECTION: &str = "packages";
+/// Packages to verify, unless configured otherwise.
+const DEFAULT_PACKAGES: [&str; 8] = [
+    "linux",
+    "glibc",
+    "systemd",
+    "xorg-server",
+    "mesa",
+    "chromium",
+    "digsigctl",
+    "application-html",
+];
+/// Reason given by pacman for files that do not exist.
+const MISSING: &str = "No such file or directory";
+/// Reason given by pacman for files that cannot be read without elevated privileges.
+const PERMISSION_DENIED: &str = "Permission denied";
+
+/// Result of the verification of the files of installed packages.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Verification {
+    verified_at: Timestamp,
+    packages: Vec<String>,
+    modified: Vec<File>,
+    missing: Vec<File>,
+    /// Files that could not be checked, since the verification runs unprivileged.
+    #[serde(default)]
+    unreadable: Vec<File>,
+    /// Packages that are not installed and thus were skipped.
+    #[serde(default)]
+    not_installed: Vec<String>,
+}
+
+/// A file that differs from the state recorded in its package.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct File {
+    package: String,
+    path: String,
+    reason: String,
+}
+
+/// Returns the packages configured to be verified.
+///
+/// # Errors
+///
+/// Returns an error message if the settings could not be loaded.
+pub fn configured_packages() -> Result<Vec<String>, String> {
+    let packages = settings::list(&settings::load()?, SECTION, "verify");
+
+    if packages.is_empty() {
+        Ok(DEFAULT_PACKAGES.map(String::from).to_vec())
+    } else {
+        Ok(packages)
+    }
+}
+
+/// Verifies the files of the given packages and stores the result.
+///
+/// Returns `None` if the job has been cancelled before all packages were verified.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] if pacman could not be run or failed for an installed package.
+pub fn verify(packages: &[String], context: &Context) -> std::io::Result<Option<Verification>> {
+    let mut verification = Verification {
+        verified_at: Timestamp::now(),
+        packages: packages.to_vec(),
+        modified: Vec::new(),
+        missing: Vec::new(),
+        unreadable: Vec::new(),
+        not_installed: Vec::new(),
+    };
+
+    for (index, package) in packages.iter().enumerate() {
+        if context.is_cancelled() {
+            return Ok(None);
+        }
+
+        context.progress(format!(
+            "Verifying {package} ({}/{})",
+            index + 1,
+            packages.len()
+        ));
+        let output = verify_files(package)?;
+        let files: Vec<File> = output
+            .stderr()
+            .lines()
+            .filter_map(parse_warning)
+            .filter(|file| &file.package == package)
+            .collect();
+
+        if !output.success() && files.is_empty() {
+            if is_not_found(output.stderr(), package) {
+                warn!("Skipping package that is not installed: {package}");
+                verification.not_installed.push(package.clone());
+                continue;
+            }
+
+            return Err(std::io::Error::other(output.stderr().trim().to_string()));
+        }
+
+        for file in files {
+            match file.reason.as_str() {
+                MISSING => verification.missing.push(file),
+                PERMISSION_DENIED => verification.unreadable.push(file),
+                _ => verification.modified.push(file),
+            }
+        }
+    }
+
+    info!(
+        "Verified {} packages: {} modified, {} missing and {} unreadable files, {} not installed",
+        packages.len(),
+        verification.modified.len(),
+        verification.missing.len(),
+        verification.unreadable.len(),
+        verification.not_installed.len()
+    );
+
+    if let Err(error) = state::save(VERIFICATION_FILE, &verification) {
+        error!("Could not save verification: {error}");
+    }
+
+    Ok(Some(verification))
+}
+
+/// Returns the latest stored verification, if any.
+pub fn latest() -> Option<Verification> {
+    state::load(VERIFICATION_FILE)
+        .inspect_err(|error| error!("Could not load verification: {error}"))
+        .ok()
+        .flatten()
+}
+
+/// Determines whether pacman reported the given package as not installed,
+/// e.g. `error: package 'mesa' was not found`.
+fn is_not_found(stderr: &str, package: &str) -> bool {
+    let message = format!("error: package '{package}' was not found");
+    stderr.lines().any(|line| line.trim_end() == message)
+}
+
+/// Parse a warning of `pacman -Qkk`, e.g. `warning: mesa: /usr/lib/libGL.so (Size mismatch)`.
+fn parse_warning(line: &str) -> Option<File> {
+    let (package, file) = line.strip_prefix("warning: ")?.split_once(": ")?;
+    let (path, reason) = file.trim_end().strip_suffix(')')?.rsplit_once(" (")?;
+    Some(File {
+        package: package.to_string(),
+        path: path.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_not_found, parse_warning, File};
+
+    #[test]
+    fn test_is_not_found() {
+        let stderr = "error: package 'application-html' was not found\n";
+        assert!(is_not_found(stderr, "application-html"));
+        assert!(!is_not_found(stderr, "application"));
+        assert!(!is_not_found(
+            "error: could not open file",
+            "application-html"
+        ));
+    }
+
+    #[test]
+    fn test_parse_warning() {
+        assert_eq!(
+            parse_warning("warning: mesa: /usr/lib/libGL.so.1.7.0 (Size mismatch)"),
+            Some(File {
+                package: "mesa".into(),
+                path: "/usr/lib/libGL.so.1.7.0".into(),
+                reason: "Size mismatch".into(),
+            })
+        );
+        assert_eq!(
+            parse_warning(
+                "warning: chromium: /usr/share/icons/hicolor (1) (No such file or directory)"
+            ),
+            Some(File {
+                package: "chromium".into(),
+                path: "/usr/share/icons/hicolor (1)".into(),
+                reason: "No such file or directory".into(),
+            })
+        );
+        assert_eq!(
+            parse_warning("mesa: 423 total files, 0 altered files"),
+            None
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Background jobs for long-running RPC commands.
+
+use crate::audit::{self, Kind};
+use crate::rpc::{Request, Result};
+use rocket::http::Status;
+use rocket::serde::json::{json, Value};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info_span};
+
+/// Maximum amount of jobs running at the same time.
+const MAX_RUNNING_JOBS: usize = 4;
+/// Maximum amount of finished jobs to retain.
+const MAX_FINISHED_JOBS: usize = 50;
+/// Duration for which finished jobs are retained.
+const RETENTION: Duration = Duration::from_hours(1);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static JOBS: Mutex<BTreeMap<u64, Arc<Handle>>> = Mutex::new(BTreeMap::new());
+
+/// State of a job.
+#[derive(Clone, Copy, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum State {
+    /// The job is still running.
+    #[serde(rename = "running")]
+    Running,
+    /// The job finished successfully.
+    #[serde(rename = "succeeded")]
+    Succeeded,
+    /// The job finished with errors.
+    #[serde(rename = "failed")]
+    Failed,
+    /// The job was cancelled before it finished.
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+/// A job running an RPC request in the background.
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct Job {
+    id: u64,
+    command: String,
+    state: State,
+    cancellable: bool,
+    progress: Option<String>,
+    created: SystemTime,
+    finished: Option<SystemTime>,
+    /// The HTTP status of the result, once finished.
+    status: Option<u16>,
+    /// The serialized result, once finished.
+    result: Option<Value>,
+}
+
+impl Job {
+    /// Returns the ID of the job.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the current state of the job.
+    #[must_use]
+    pub const fn state(&self) -> State {
+        self.state
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.finished
+            .and_then(|finished| finished.checked_add(RETENTION))
+            .is_some_and(|expires| expires <= now)
+    }
+}
+
+/// Reference to a submitted job as returned to the client.
+#[derive(Clone, Copy, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct JobId {
+    job: u64,
+}
+
+#[derive(Debug)]
+struct Handle {
+    job: Mutex<Job>,
+    cancelled: AtomicBool,
+}
+
+impl Handle {
+    fn job(&self) -> Option<MutexGuard<'_, Job>> {
+        self.job.lock().ok()
+    }
+}
+
+/// Context of a running job.
+///
+/// Long-running tasks use it to report their progress and to check for cancellation.
+/// The default context does not belong to any job.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    handle: Option<Arc<Handle>>,
+}
+
+impl Context {
+    /// Determines whether cancellation of the job has been requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| handle.cancelled.load(Ordering::Relaxed))
+    }
+
+    /// Reports the progress of the job.
+    pub fn progress(&self, progress: impl Into<String>) {
+        if let Some(mut job) = self.handle.as_ref().and_then(|handle| handle.job()) {
+            job.progress.replace(progress.into());
+        }
+    }
+}
+
+/// Runs the given request as a background job and returns the job's ID as a [`Result`].
+///
+/// The request is rejected, if too many jobs are already running.
+/// Once the job has finished, its outcome is audited as the given kind of action.
+#[must_use]
+pub fn submit(request: Request, kind: Kind, remote: Option<SocketAddr>) -> Result {
+    let Ok(mut jobs) = JOBS.lock() else {
+        return Result::Error(
+            (
+                "Could not access jobs.".to_string(),
+                Status::InternalServerError,
+            )
+                .into(),
+        );
+    };
+    prune(&mut jobs);
+
+    if jobs
+        .values()
+        .filter(|handle| handle.job().is_some_and(|job| job.state == State::Running))
+        .count()
+        >= MAX_RUNNING_JOBS
+    {
+        return Result::Error(
+            (
+                format!("Too many running jobs, at most {MAX_RUNNING_JOBS} are allowed."),
+                Status::TooManyRequests,
+            )
+                .into(),
+        );
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let handle = Arc::new(Handle {
+        job: Mutex::new(Job {
+            id,
+            command: request.name(),
+            state: State::Running,
+            cancellable: request.is_cancellable(),
+            progress: None,
+            created: SystemTime::now(),
+            finished: None,
+            status: None,
+            result: None,
+        }),
+        cancelled: AtomicBool::new(false),
+    });
+
+    jobs.insert(id, handle.clone());
+    drop(jobs);
+
+    let _ = thread::spawn(move || {
+        let _span = info_span!("job", job_id = id, command = request.name()).entered();
+        let context = Context {
+            handle: Some(handle.clone()),
+        };
+        let result = request.run_with(&context);
+        finish(&handle, &result);
+        audit::record(
+            kind,
+            remote,
+            &json!({"job": id, "request": request}),
+            result.status(),
+        );
+    });
+
+    Result::Success(Box::new(JobId { job: id }))
+}
+
+/// Returns all retained jobs, oldest first.
+#[must_use]
+pub fn list() -> Vec<Job> {
+    JOBS.lock()
+        .map(|mut jobs| {
+            prune(&mut jobs);
+            jobs.values()
+                .filter_map(|handle| handle.job().map(|job| job.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the job with the given ID as a [`Result`].
+#[must_use]
+pub fn get(id: u64) -> Result {
+    lookup(id)
+        .and_then(|handle| handle.job().map(|job| job.clone()))
+        .map_or_else(|| not_found(id), |job| Result::Success(Box::new(job)))
+}
+
+/// Requests cancellation of the job with the given ID.
+///
+/// The job will stop at the next opportunity, e.g. before the next command of a batch.
+#[must_use]
+pub fn cancel(id: u64) -> Result {
+    let Some(handle) = lookup(id) else {
+        return not_found(id);
+    };
+    let Some(job) = handle.job().map(|job| job.clone()) else {
+        return not_found(id);
+    };
+
+    if !job.cancellable {
+        return Result::Error((format!("Job {id} cannot be cancelled."), Status::Conflict).into());
+    }
+
+    if job.state != State::Running {
+        return Result::Error((format!("Job {id} has already finished."), Status::Conflict).into());
+    }
+
+    handle.cancelled.store(true, Ordering::Relaxed);
+    Result::Success(Box::new(job))
+}
+
+fn lookup(id: u64) -> Option<Arc<Handle>> {
+    JOBS.lock().ok().and_then(|mut jobs| {
+        prune(&mut jobs);
+        jobs.get(&id).cloned()
+    })
+}
+
+fn finish(handle: &Handle, result: &Result) {
+    let Some(mut job) = handle.job() else {
+        error!("Could not record result of job");
+        return;
+    };
+
+    job.state = if handle.cancelled.load(Ordering::Relaxed) {
+        State::Cancelled
+    } else if matches!(result, Result::Error(_) | Result::MultiStatus(_)) {
+        State::Failed
+    } else {
+        State::Succeeded
+    };
+    job.finished = Some(SystemTime::now());
+    job.status = Some(result.status().code);
+    job.result = result
+        .to_value()
+        .inspect_err(|error| error!("Could not serialize result of job: {error}"))
+        .ok();
+}
+
+/// Discards expired jobs and the oldest finished jobs exceeding the retention limit.
+fn prune(jobs: &mut BTreeMap<u64, Arc<Handle>>) {
+    let now = SystemTime::now();
+    let mut finished: Vec<u64> = Vec::new();
+    jobs.retain(|id, handle| {
+        handle.job().is_none_or(|job| {
+            if job.is_expired(now) {
+                return false;
+            }
+
+            if job.finished.is_some() {
+                finished.push(*id);
+            }
+
+            true
+        })
+    });
+
+    for id in finished
+        .iter()
+        .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+    {
+        jobs.remove(id);
+    }
+}
+
+fn not_found(id: u64) -> Result {
+    Result::Error((format!("No such job: {id}"), Status::NotFound).into())
+}
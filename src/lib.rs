@@ -2,23 +2,48 @@
 //!
 //! This library exposes functions and datastructures as used
 //! by the programs `digsigctl` and `fix-chromium-preferences`.
+pub mod audit;
+mod capabilities;
+pub mod client;
 mod config;
 pub mod constants;
+mod control_channel;
+pub mod history;
+mod identity;
+mod integrity;
+pub mod jobs;
+pub mod logging;
 mod net;
+pub mod openapi;
 mod pacman;
 pub mod portal;
+mod push;
+mod reconcile;
 mod rpc;
 mod screenshot;
+mod settings;
+mod state;
 mod sudo;
 mod sysinfo;
 mod systemctl;
+mod timestamp;
 mod try_from_io;
 
+pub use crate::sysinfo::v2::SystemInformation as SystemInformationV2;
 pub use crate::sysinfo::SystemInformation;
 pub use crate::systemctl::is_active;
-pub use config::{ChromiumPreferences, Config};
-pub use net::discover_address_or_exit;
-pub use portal::{apply_portal_config_if_needed, apply_portal_config_on_startup, verify_startup_page};
+pub use capabilities::Capabilities;
+pub use config::{ChromiumPreferences, Config, UrlPolicy};
+pub use control_channel::spawn_control_channel;
+pub use net::{
+    serve_on_addresses, set_networks, watch_addresses, Network, Stopped, DEFAULT_NETWORK,
+};
+pub use portal::{
+    apply_portal_config_if_needed, apply_portal_config_on_startup, verify_startup_page,
+};
+pub use push::spawn_heartbeat;
+pub use reconcile::spawn_reconciler;
 pub use rpc::default_preferences_file;
-pub use rpc::{Command, Result};
+pub use rpc::operation_mode::OperationMode;
+pub use rpc::{Batch, Command, Request, Result};
 pub use screenshot::{take_screenshot, ScreenshotResponse};
@@ -0,0 +1,93 @@
+//! Structured logging to the journal and correlation of requests.
+
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Log filter used if none or an invalid one was given.
+pub const DEFAULT_FILTER: &str = "info";
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Initializes structured logging.
+///
+/// The `filter` takes the form of `tracing` directives, e.g. `info,digsigctl::reconcile=debug`,
+/// and can be overridden by the environment variable `RUST_LOG`.
+///
+/// If the systemd journal is available, events are sent to it natively,
+/// so that the fields of the event and its enclosing spans, such as `UNIT`, `COMMAND`
+/// and `REQUEST_ID`, can be used to filter the journal.
+/// Otherwise, events are written to `stderr`.
+///
+/// This also captures the logs of the web server.
+pub fn init(filter: &str) {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(filter))
+        .unwrap_or_else(|error| {
+            eprintln!("Invalid log filter: {error}");
+            EnvFilter::new(DEFAULT_FILTER)
+        });
+    let journald = tracing_journald::layer()
+        .map(|layer| layer.with_field_prefix(None))
+        .ok();
+    let stderr = journald
+        .is_none()
+        .then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+
+    if let Err(error) = tracing_subscriber::registry()
+        .with(filter)
+        .with(journald)
+        .with(stderr)
+        .try_init()
+    {
+        eprintln!("Could not initialize logging: {error}");
+    }
+}
+
+/// ID to correlate the log events of a request.
+///
+/// It is taken from the `X-Request-Id` header, if present, or generated otherwise.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generates a new request ID, which is unique to this process.
+    #[must_use]
+    pub fn generate() -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self(format!(
+            "{time:x}-{:x}",
+            REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(
+            request
+                .headers()
+                .get_one(REQUEST_ID_HEADER)
+                .map_or_else(Self::generate, |id| Self(id.to_string())),
+        )
+    }
+}
@@ -1,41 +1,583 @@
+use futures_util::future::try_join_all;
+use futures_util::FutureExt;
 use ipnetwork::IpNetwork;
 use local_ip_address::list_afinet_netifas;
+use std::fmt::{self, Display, Formatter};
+use std::fs::read_to_string;
+use std::future::Future;
+use std::io::{BufRead, BufReader};
 use std::net::IpAddr;
-use std::process::exit;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::str::FromStr;
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::sleep;
+use tracing::{error, info, info_span, warn};
 
-/// Discovers a local IP address of the system within the network as specified by the `network`.
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const IP: &str = "/usr/bin/ip";
+/// Maximum length of the name of a network interface, as limited by the kernel.
+const MAX_INTERFACE_NAME_LENGTH: usize = 15;
+/// Time to wait before restarting the address monitor, if it exited,
+/// or between checks of the addresses, if `ip` is not installed.
+const MONITOR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// The network to listen in, unless configured otherwise.
+pub const DEFAULT_NETWORK: &str = "fd56:1dda:8794:cb90::/64";
+
+static NETWORKS: RwLock<Vec<Network>> = RwLock::new(Vec::new());
+static LISTEN_ADDRESSES: RwLock<Vec<IpAddr>> = RwLock::new(Vec::new());
+
+/// Reason why a server stopped running on an address.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Stopped<E> {
+    /// The server has been shut down, e.g. because the address changed or on a signal.
+    ShutDown,
+    /// The server could not bind to the address.
+    BindFailed(E),
+}
+
+/// A network to listen in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Network {
+    /// An IPv4 or IPv6 network, e.g. `fd56:1dda:8794:cb90::/64`.
+    Ip(IpNetwork),
+    /// The name of a network interface, e.g. `wg0`.
+    Interface(String),
+}
+
+impl Network {
+    /// Checks whether the given address of the given interface lies within this network.
+    ///
+    /// IPv6 link-local addresses of an interface are ignored, since they cannot be bound to
+    /// without a scope.
+    fn contains(&self, interface: &str, address: IpAddr) -> bool {
+        match self {
+            Self::Ip(network) => network.contains(address),
+            Self::Interface(name) => {
+                name == interface
+                    && !matches!(address, IpAddr::V6(address) if address.is_unicast_link_local())
+            }
+        }
+    }
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(network) => network.fmt(f),
+            Self::Interface(name) => name.fmt(f),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(network) = IpNetwork::from_str(s) {
+            return Ok(Self::Ip(network));
+        }
+
+        if s.is_empty()
+            || s.len() > MAX_INTERFACE_NAME_LENGTH
+            || s.contains(|char: char| char == '/' || char.is_whitespace())
+        {
+            return Err(format!("Neither an IP network nor an interface name: {s}"));
+        }
+
+        Ok(Self::Interface(s.to_string()))
+    }
+}
+
+/// Watches the addresses of the system for those within the given networks.
+///
+/// The returned receiver holds the addresses to listen on and is updated whenever
+/// any of them changes, appears or vanishes. The addresses are ordered by the network they lie
+/// within, in the given order, and each address is held only once.
+/// Changes are detected by monitoring the kernel's address notifications via `ip monitor`,
+/// or by checking the addresses periodically, if `ip` is not installed.
 ///
-/// This will exit the program, if the passed-in string is not a valid IP network
-/// or if no appropriate IP within that IP network can be found on the system.
+/// The current addresses are also remembered as the addresses the server listens on.
 #[must_use]
-pub fn discover_address_or_exit(network: &str) -> IpAddr {
-    discover_address(IpNetwork::from_str(network).unwrap_or_else(|error| {
-        eprintln!("{error}");
-        exit(1)
-    }))
-    .unwrap_or_else(|| {
-        eprintln!("No address found");
-        exit(2);
-    })
+pub fn watch_addresses(networks: Vec<Network>) -> watch::Receiver<Vec<IpAddr>> {
+    let (sender, receiver) = watch::channel(update_addresses(&networks, &[]));
+
+    let _ = thread::spawn(move || {
+        let _span = info_span!("address_monitor").entered();
+        let monitor = Path::new(IP).is_file();
+
+        if !monitor {
+            warn!("{IP} not found, checking addresses every {MONITOR_RESTART_DELAY:?}");
+        }
+
+        while !sender.is_closed() {
+            if monitor {
+                if let Err(error) = monitor_addresses(&networks, &sender) {
+                    error!("Could not monitor addresses: {error}");
+                }
+            }
+
+            thread::sleep(MONITOR_RESTART_DELAY);
+            // Changes may have been missed while the monitor was not running.
+            refresh(&networks, &sender);
+        }
+    });
+
+    receiver
 }
 
-/// Discovers a local IP address within the given network.
+/// Runs a server on each address held by `addresses` and restarts them whenever the addresses change.
 ///
-/// This is used to find the system's VPN IP address on which we want to listen,
-/// since we don't want to expose `digsigctl` to the entire internet.
+/// `serve` runs the server on the given address until it is shut down. It also receives a clone
+/// of `addresses`, which changes when the server shall shut down to be re-bound.
+/// While there is no address, this waits for one or until `shutdown` resolves.
+/// If binding to an address fails, it is retried after `retry_delay`, until the addresses change
+/// or `shutdown` resolves, while the servers on the other addresses keep running.
 ///
 /// # Errors
-/// This function will return `None` if no appropriate IP address could be found on the system.
-#[must_use]
-fn discover_address(network: IpNetwork) -> Option<IpAddr> {
-    if let Ok(network_interfaces) = list_afinet_netifas() {
-        for (_, ip) in network_interfaces {
-            if network.contains(ip) {
-                return Some(ip);
+///
+/// Returns the error of `serve`, if any server failed for another reason than binding.
+pub async fn serve_on_addresses<S, F, E>(
+    mut addresses: watch::Receiver<Vec<IpAddr>>,
+    retry_delay: Duration,
+    shutdown: impl Future<Output = ()> + Send,
+    serve: S,
+) -> Result<(), E>
+where
+    S: Fn(IpAddr, watch::Receiver<Vec<IpAddr>>) -> F + Sync,
+    F: Future<Output = Result<Stopped<E>, E>> + Send,
+    E: Display + Send,
+{
+    let shutdown = shutdown.shared();
+
+    loop {
+        let current = addresses.borrow_and_update().clone();
+
+        if current.is_empty() {
+            info!("Waiting for an address to listen on");
+
+            tokio::select! {
+                changed = addresses.changed() => if changed.is_err() {
+                    return Ok(());
+                },
+                () = shutdown.clone() => return Ok(()),
             }
+
+            continue;
         }
+
+        try_join_all(current.into_iter().map(|address| {
+            serve_on_address(
+                address,
+                addresses.clone(),
+                retry_delay,
+                shutdown.clone(),
+                &serve,
+            )
+        }))
+        .await?;
+
+        // The servers have been shut down, because the addresses changed.
+        if addresses.has_changed().unwrap_or(false) {
+            info!("Re-binding servers to changed addresses");
+        } else {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns the addresses the server listens on, as far as they have been discovered.
+pub fn listen_addresses() -> Vec<IpAddr> {
+    LISTEN_ADDRESSES
+        .read()
+        .map(|listen_addresses| listen_addresses.clone())
+        .unwrap_or_default()
+}
+
+/// Sets the networks to listen in, as configured on the command line.
+pub fn set_networks(networks: Vec<Network>) {
+    if let Ok(mut configured) = NETWORKS.write() {
+        *configured = networks;
     }
+}
+
+/// Returns the system's address within the configured networks.
+///
+/// If there are several, this is the first one of the first network that has any.
+/// Unlike [`listen_addresses`], this does not depend on the server running.
+pub fn local_address() -> Option<IpAddr> {
+    find_address(&networks(), &list_afinet_netifas().ok()?).map(|(_, address)| *address)
+}
+
+/// Returns the network interface holding the system's address within the configured networks.
+///
+/// If there is no such address, e.g. while the VPN is down,
+/// this falls back to the first configured interface, that exists.
+pub fn local_interface() -> Option<String> {
+    let networks = networks();
+    list_afinet_netifas()
+        .ok()
+        .and_then(|network_interfaces| {
+            find_address(&networks, &network_interfaces).map(|(interface, _)| interface.clone())
+        })
+        .or_else(|| {
+            networks.into_iter().find_map(|network| match network {
+                Network::Interface(name) => PathBuf::from(SYS_CLASS_NET)
+                    .join(&name)
+                    .exists()
+                    .then_some(name),
+                Network::Ip(_) => None,
+            })
+        })
+}
+
+/// Returns the MAC address of the given network interface.
+///
+/// # Errors
+///
+/// This function will return an [`std::io::Error`] if the address could not be read from sysfs.
+pub fn mac_address(interface: &str) -> std::io::Result<String> {
+    read_to_string(PathBuf::from(SYS_CLASS_NET).join(interface).join("address"))
+        .map(|address| address.trim().to_string())
+}
+
+/// Runs `ip monitor` and updates the addresses on every change of the system's addresses.
+///
+/// Returns when the monitor exits or the receiving end has been dropped.
+fn monitor_addresses(
+    networks: &[Network],
+    sender: &watch::Sender<Vec<IpAddr>>,
+) -> std::io::Result<()> {
+    let mut child = Command::new(IP)
+        .args(["-oneline", "monitor", "address"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| std::io::Error::other("No stdout"))?;
+
+    for line in BufReader::new(stdout).lines() {
+        line?;
+
+        if sender.is_closed() {
+            break;
+        }
 
-    None
+        refresh(networks, sender);
+    }
+
+    // The monitor may still be running, if the receiving end has been dropped.
+    let _ = child.kill();
+    child.wait().map(drop)
+}
+
+/// Runs a server on the given address and retries binding to it until the addresses change.
+async fn serve_on_address<S, F, E>(
+    address: IpAddr,
+    mut addresses: watch::Receiver<Vec<IpAddr>>,
+    retry_delay: Duration,
+    shutdown: impl Future<Output = ()> + Send,
+    serve: &S,
+) -> Result<(), E>
+where
+    S: Fn(IpAddr, watch::Receiver<Vec<IpAddr>>) -> F + Sync,
+    F: Future<Output = Result<Stopped<E>, E>> + Send,
+    E: Display + Send,
+{
+    tokio::pin!(shutdown);
+
+    loop {
+        match serve(address, addresses.clone()).await? {
+            Stopped::ShutDown => return Ok(()),
+            Stopped::BindFailed(error) => {
+                warn!("Could not bind to {address}: {error}");
+
+                tokio::select! {
+                    () = sleep(retry_delay) => {}
+                    _ = addresses.changed() => return Ok(()),
+                    () = &mut shutdown => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Updates the addresses to listen on and notifies the receivers, if they changed.
+fn refresh(networks: &[Network], sender: &watch::Sender<Vec<IpAddr>>) {
+    sender.send_if_modified(|addresses| {
+        let current = update_addresses(networks, addresses);
+        let modified = current != *addresses;
+        *addresses = current;
+        modified
+    });
+}
+
+/// Discovers the addresses to listen on and remembers them, if they differ from the current ones.
+fn update_addresses(networks: &[Network], current: &[IpAddr]) -> Vec<IpAddr> {
+    let addresses = list_afinet_netifas()
+        .inspect_err(|error| error!("Could not list addresses: {error}"))
+        .map(|network_interfaces| select_addresses(networks, &network_interfaces))
+        .unwrap_or_default();
+
+    if addresses != current {
+        if addresses.is_empty() {
+            warn!("No address found in networks: {}", join(networks));
+        } else {
+            info!("Discovered addresses: {}", join(&addresses));
+        }
+
+        if let Ok(mut listen_addresses) = LISTEN_ADDRESSES.write() {
+            listen_addresses.clone_from(&addresses);
+        }
+    }
+
+    addresses
+}
+
+fn join<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the configured networks or, if none have been configured, the default network.
+fn networks() -> Vec<Network> {
+    NETWORKS
+        .read()
+        .ok()
+        .map(|networks| networks.clone())
+        .filter(|networks| !networks.is_empty())
+        .or_else(|| DEFAULT_NETWORK.parse().ok().map(|network| vec![network]))
+        .unwrap_or_default()
+}
+
+/// Selects the addresses of the system within the given networks.
+///
+/// This is used to find the system's VPN IP addresses on which we want to listen,
+/// since we don't want to expose `digsigctl` to the entire internet.
+fn select_addresses(networks: &[Network], network_interfaces: &[(String, IpAddr)]) -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+
+    for network in networks {
+        for (interface, address) in network_interfaces {
+            if network.contains(interface, *address) && !addresses.contains(address) {
+                addresses.push(*address);
+            }
+        }
+    }
+
+    addresses
+}
+
+/// Finds the interface and address of the system within the first network, that has one.
+fn find_address<'a>(
+    networks: &[Network],
+    network_interfaces: &'a [(String, IpAddr)],
+) -> Option<&'a (String, IpAddr)> {
+    networks.iter().find_map(|network| {
+        network_interfaces
+            .iter()
+            .find(|(interface, address)| network.contains(interface, *address))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_addresses, serve_on_addresses, Network, Stopped};
+    use std::future::{pending, ready};
+    use std::net::IpAddr;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::watch;
+
+    const RETRY_DELAY: Duration = Duration::from_millis(1);
+
+    fn network_interfaces() -> Vec<(String, IpAddr)> {
+        vec![
+            ("lo".into(), "127.0.0.1".parse().unwrap()),
+            ("eth0".into(), "192.168.0.10".parse().unwrap()),
+            ("wg0".into(), "fe80::1".parse().unwrap()),
+            ("wg0".into(), "10.8.0.5".parse().unwrap()),
+            ("wg0".into(), "fd56:1dda:8794:cb90::5".parse().unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_parse_network() {
+        assert_eq!(
+            "fd56:1dda:8794:cb90::/64".parse::<Network>(),
+            Ok(Network::Ip("fd56:1dda:8794:cb90::/64".parse().unwrap()))
+        );
+        assert_eq!(
+            "10.8.0.0/24".parse::<Network>(),
+            Ok(Network::Ip("10.8.0.0/24".parse().unwrap()))
+        );
+        assert_eq!(
+            "wg0".parse::<Network>(),
+            Ok(Network::Interface("wg0".into()))
+        );
+        assert!("10.8.0.0/33".parse::<Network>().is_err());
+        assert!("".parse::<Network>().is_err());
+        assert!("an-overly-long-interface".parse::<Network>().is_err());
+    }
+
+    #[test]
+    fn test_select_addresses() {
+        let network_interfaces = network_interfaces();
+        let networks: Vec<Network> = ["10.9.0.0/24", "fd56:1dda:8794:cb90::/64", "10.8.0.0/24"]
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect();
+        assert_eq!(
+            select_addresses(&networks, &network_interfaces),
+            vec![
+                "fd56:1dda:8794:cb90::5".parse::<IpAddr>().unwrap(),
+                "10.8.0.5".parse().unwrap()
+            ]
+        );
+        assert!(select_addresses(&networks[..1], &network_interfaces).is_empty());
+        assert_eq!(
+            select_addresses(&[Network::Interface("wg0".into())], &network_interfaces),
+            vec![
+                "10.8.0.5".parse::<IpAddr>().unwrap(),
+                "fd56:1dda:8794:cb90::5".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_addresses_once() {
+        let network_interfaces = network_interfaces();
+        let networks: Vec<Network> = ["10.8.0.0/24", "wg0", "192.168.0.0/24"]
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect();
+        assert_eq!(
+            select_addresses(&networks, &network_interfaces),
+            vec![
+                "10.8.0.5".parse::<IpAddr>().unwrap(),
+                "fd56:1dda:8794:cb90::5".parse().unwrap(),
+                "192.168.0.10".parse().unwrap()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve_binds_each_address() {
+        let addresses: Vec<IpAddr> = vec!["10.8.0.5".parse().unwrap(), "fd56::5".parse().unwrap()];
+        let (_sender, receiver) = watch::channel(addresses.clone());
+        let served = Mutex::new(Vec::new());
+
+        let result: Result<(), String> =
+            serve_on_addresses(receiver, RETRY_DELAY, pending(), |address, _| {
+                served.lock().unwrap().push(address);
+                ready(Ok(Stopped::ShutDown))
+            })
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(*served.lock().unwrap(), addresses);
+    }
+
+    #[tokio::test]
+    async fn test_serve_rebinds_on_change() {
+        let first: IpAddr = "10.8.0.5".parse().unwrap();
+        let second: IpAddr = "10.8.0.6".parse().unwrap();
+        let (sender, receiver) = watch::channel(vec![first]);
+        let served = Mutex::new(Vec::new());
+
+        let result: Result<(), String> = serve_on_addresses(
+            receiver,
+            RETRY_DELAY,
+            pending(),
+            |address, mut addresses| {
+                served.lock().unwrap().push(address);
+                let sender = &sender;
+                async move {
+                    if address == first {
+                        sender.send_replace(vec![second]);
+                        addresses.changed().await.unwrap();
+                    }
+
+                    Ok(Stopped::ShutDown)
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(*served.lock().unwrap(), vec![first, second]);
+    }
+
+    #[tokio::test]
+    async fn test_serve_waits_for_address() {
+        let address: IpAddr = "10.8.0.5".parse().unwrap();
+        let (sender, receiver) = watch::channel(Vec::new());
+        let served = Mutex::new(Vec::new());
+        let send = tokio::spawn(async move {
+            tokio::time::sleep(RETRY_DELAY).await;
+            sender.send_replace(vec![address]);
+        });
+
+        let result: Result<(), String> =
+            serve_on_addresses(receiver, RETRY_DELAY, pending(), |address, _| {
+                served.lock().unwrap().push(address);
+                ready(Ok(Stopped::ShutDown))
+            })
+            .await;
+        send.await.unwrap();
+        assert_eq!(result, Ok(()));
+        assert_eq!(*served.lock().unwrap(), vec![address]);
+    }
+
+    #[tokio::test]
+    async fn test_serve_retries_bind() {
+        let address: IpAddr = "10.8.0.5".parse().unwrap();
+        let (_sender, receiver) = watch::channel(vec![address]);
+        let served = Mutex::new(Vec::new());
+
+        let result: Result<(), String> =
+            serve_on_addresses(receiver, RETRY_DELAY, pending(), |address, _| {
+                let mut served = served.lock().unwrap();
+                served.push(address);
+                ready(if served.len() == 1 {
+                    Ok(Stopped::BindFailed("Address in use".into()))
+                } else {
+                    Ok(Stopped::ShutDown)
+                })
+            })
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(*served.lock().unwrap(), vec![address, address]);
+    }
+
+    #[tokio::test]
+    async fn test_serve_stops_retrying_on_shutdown() {
+        let address: IpAddr = "10.8.0.5".parse().unwrap();
+        let (_sender, receiver) = watch::channel(vec![address]);
+
+        let result: Result<(), String> =
+            serve_on_addresses(receiver, Duration::MAX, ready(()), |_, _| {
+                ready(Ok(Stopped::BindFailed("Address in use".into())))
+            })
+            .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_serve_shuts_down_without_address() {
+        let (_sender, receiver) = watch::channel(Vec::new());
+
+        let result: Result<(), String> =
+            serve_on_addresses(receiver, RETRY_DELAY, ready(()), |_, _| {
+                ready(Err("No address expected".into()))
+            })
+            .await;
+        assert_eq!(result, Ok(()));
+    }
 }
@@ -0,0 +1,290 @@
+//! `OpenAPI` description of the RPC server's interface.
+//!
+//! The schemas are generated from the Rust types, so that they always reflect
+//! the serialization as actually performed by the server.
+
+use crate::capabilities::Capabilities;
+use crate::jobs::{Job, JobId};
+use crate::rpc::{BatchResult, Command, Request, Result};
+use crate::sysinfo::{v2, SystemInformation};
+use crate::Config;
+use rocket::serde::json::{json, Value};
+use schemars::generate::SchemaSettings;
+use schemars::SchemaGenerator;
+
+/// Version of the API.
+///
+/// This must be bumped whenever the schema changes.
+pub const API_VERSION: &str = "1.24.0";
+const DEFINITIONS_PATH: &str = "/components/schemas";
+
+/// Returns the `OpenAPI` document describing the RPC server's interface.
+#[allow(clippy::too_many_lines)]
+#[must_use]
+pub fn openapi() -> Value {
+    // Requests are described as accepted by the server and responses as sent by it.
+    let mut requests = generator(SchemaSettings::for_deserialize);
+    let mut responses = generator(SchemaSettings::for_serialize);
+    let config = requests.subschema_for::<Config>();
+    let request = requests.subschema_for::<Request>();
+    let sysinfo = responses.subschema_for::<SystemInformation>();
+    let sysinfo_v2 = responses.subschema_for::<v2::SystemInformation>();
+    let capabilities = responses.subschema_for::<Capabilities>();
+    let job = responses.subschema_for::<Job>();
+    let jobs = responses.subschema_for::<Vec<Job>>();
+    let job_id = responses.subschema_for::<JobId>();
+    let command_result = Command::results_schema(&mut responses);
+    let batch_result = responses.subschema_for::<BatchResult>();
+    let errors = Result::errors_schema(&mut responses);
+    let mut schemas = requests.take_definitions(true);
+    schemas.extend(responses.take_definitions(true));
+    let error = |description: &str| {
+        json!({
+            "description": description,
+            "content": {"application/json": {"schema": errors}},
+        })
+    };
+    let job_path = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": {"type": "integer", "minimum": 0},
+    });
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "digsigctl",
+            "description": "Digital signage system controller",
+            "version": API_VERSION,
+        },
+        "paths": {
+            "/configure": {
+                "post": {
+                    "summary": "Apply a configuration",
+                    "description": "Superseded by /v2/configure",
+                    "requestBody": {
+                        "required": true,
+                        "content": {"application/json": {"schema": config}},
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "A message describing the outcome",
+                            "content": {"text/plain": {"schema": {"type": "string"}}},
+                        },
+                    },
+                },
+            },
+            "/v2/configure": {
+                "post": {
+                    "summary": "Apply a configuration and report failures with an error status",
+                    "requestBody": {
+                        "required": true,
+                        "content": {"application/json": {"schema": config}},
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "A message describing the outcome",
+                            "content": {"application/json": {"schema": {"type": "string"}}},
+                        },
+                        "default": error("The configuration could not be applied"),
+                    },
+                },
+            },
+            "/rpc": {
+                "post": {
+                    "summary": "Run an RPC command or a batch of commands",
+                    "parameters": [{
+                        "name": "background",
+                        "in": "query",
+                        "description": "Run the request as a background job",
+                        "schema": {"type": "boolean"},
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": {"application/json": {"schema": request}},
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The command's result, the results of a batch \
+                                or the job, if run in the background",
+                            "content": {"application/json": {"schema": {
+                                "anyOf": [command_result, batch_result, job_id],
+                            }}},
+                        },
+                        "207": {
+                            "description": "The results of a batch, of which some commands failed",
+                            "content": {"application/json": {"schema": batch_result}},
+                        },
+                        "429": error("Too many background jobs are running"),
+                        "default": error("The errors that occurred"),
+                    },
+                },
+            },
+            "/sysinfo": {
+                "get": {
+                    "summary": "Get information about the system",
+                    "description": "Superseded by /v2/sysinfo",
+                    "responses": {
+                        "200": {
+                            "description": "The system information",
+                            "content": {"application/json": {"schema": sysinfo}},
+                        },
+                    },
+                },
+            },
+            "/v2/sysinfo": {
+                "get": {
+                    "summary": "Get information about the system in the versioned format",
+                    "responses": {
+                        "200": {
+                            "description": "The system information",
+                            "content": {"application/json": {"schema": sysinfo_v2}},
+                        },
+                    },
+                },
+            },
+            "/capabilities": {
+                "get": {
+                    "summary": "Get the capabilities of the system",
+                    "responses": {
+                        "200": {
+                            "description": "The capabilities",
+                            "content": {"application/json": {"schema": capabilities}},
+                        },
+                    },
+                },
+            },
+            "/screenshot": {
+                "get": {
+                    "summary": "Take a screenshot",
+                    "responses": {
+                        "200": {
+                            "description": "The screenshot",
+                            "content": {"image/png": {}},
+                        },
+                        "500": {
+                            "description": "The screenshot could not be taken",
+                            "content": {"text/plain": {"schema": {"type": "string"}}},
+                        },
+                    },
+                },
+            },
+            "/jobs": {
+                "get": {
+                    "summary": "List background jobs",
+                    "responses": {
+                        "200": {
+                            "description": "The retained jobs, oldest first",
+                            "content": {"application/json": {"schema": jobs}},
+                        },
+                    },
+                },
+            },
+            "/jobs/{id}": {
+                "get": {
+                    "summary": "Get a background job",
+                    "parameters": [job_path],
+                    "responses": {
+                        "200": {
+                            "description": "The job",
+                            "content": {"application/json": {"schema": job}},
+                        },
+                        "404": error("No such job"),
+                    },
+                },
+                "delete": {
+                    "summary": "Cancel a background job",
+                    "parameters": [job_path],
+                    "responses": {
+                        "200": {
+                            "description": "The job, which will stop at the next opportunity",
+                            "content": {"application/json": {"schema": job}},
+                        },
+                        "404": error("No such job"),
+                        "409": error("The job cannot be cancelled"),
+                    },
+                },
+            },
+            "/verify-portal": {
+                "get": {
+                    "summary": "Verify that the portal URL matches the startup page",
+                    "responses": {
+                        "200": {
+                            "description": "A message describing the outcome",
+                            "content": {"text/plain": {"schema": {"type": "string"}}},
+                        },
+                    },
+                },
+            },
+            "/portal-url": {
+                "get": {
+                    "summary": "Get the portal URL for this system",
+                    "responses": {
+                        "200": {
+                            "description": "The URL or a message describing the error",
+                            "content": {"text/plain": {"schema": {"type": "string"}}},
+                        },
+                    },
+                },
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "Get this document",
+                    "responses": {
+                        "200": {
+                            "description": "The OpenAPI document",
+                            "content": {"application/json": {}},
+                        },
+                    },
+                },
+            },
+        },
+        "components": {"schemas": schemas},
+    })
+}
+
+fn generator(contract: fn(SchemaSettings) -> SchemaSettings) -> SchemaGenerator {
+    contract(SchemaSettings::draft2020_12().with(|settings| {
+        settings.definitions_path = DEFINITIONS_PATH.into();
+    }))
+    .into_generator()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{openapi, API_VERSION};
+    use rocket::serde::json::{serde_json, Value};
+    use std::env::var_os;
+    use std::fs::{read_to_string, write};
+    use std::path::PathBuf;
+
+    /// Set this environment variable to overwrite the snapshot with a new API version.
+    const UPDATE_SNAPSHOT: &str = "UPDATE_OPENAPI_SNAPSHOT";
+
+    #[test]
+    fn test_schema_changes_require_version_bump() {
+        let snapshot = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("openapi.json");
+        let document = openapi();
+        let expected: Value = serde_json::from_str(&read_to_string(&snapshot).unwrap()).unwrap();
+        let version = &expected["info"]["version"];
+
+        // The snapshot may only be overwritten by a new version.
+        if var_os(UPDATE_SNAPSHOT).is_some() && version != API_VERSION {
+            write(
+                &snapshot,
+                format!("{}\n", serde_json::to_string_pretty(&document).unwrap()),
+            )
+            .unwrap();
+            return;
+        }
+
+        assert!(
+            document == expected,
+            "The API schema differs from the snapshot of version {version}. \
+            Bump API_VERSION and overwrite the snapshot by setting {UPDATE_SNAPSHOT}."
+        );
+    }
+}
@@ -1,8 +1,94 @@
+use std::path::Path;
 use std::process::{Command, Stdio};
 
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::sudo::sudo;
 use crate::try_from_io::TryFromIo;
 
 const PACMAN: &str = "/usr/bin/pacman";
+/// Checks for updates using temporary databases, provided by `pacman-contrib`.
+const CHECKUPDATES: &str = "/usr/bin/checkupdates";
+/// Wrapper to upgrade the entire system, which takes no arguments.
+const UPGRADE: &str = "/usr/lib/digsigctl/upgrade";
+const LOCK_FILE: &str = "/var/lib/pacman/db.lck";
+
+/// An installed package.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Package {
+    name: String,
+    version: String,
+}
+
+impl Package {
+    /// Returns the name of the package.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the installed version of the package.
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+/// An available update of an installed package.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Update {
+    name: String,
+    installed: String,
+    available: String,
+}
+
+impl Update {
+    /// Returns the name of the package.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The captured output of a pacman run.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Output {
+    #[serde(rename = "exitCode")]
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+impl Output {
+    /// Determines whether pacman exited successfully.
+    #[must_use]
+    pub const fn success(&self) -> bool {
+        matches!(self.exit_code, Some(0))
+    }
+
+    /// Returns the exit code of pacman, unless it was terminated by a signal.
+    #[must_use]
+    pub const fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Returns the captured standard error output.
+    #[must_use]
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+}
+
+impl From<std::process::Output> for Output {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+}
 
 /// Create pacman commands.
 pub fn pacman(args: &[&str]) -> Command {
@@ -31,3 +117,132 @@ pub fn package_version(package: &str) -> std::io::Result<String> {
         },
     )
 }
+
+/// Retrieve all installed packages.
+pub fn installed_packages() -> std::io::Result<Vec<Package>> {
+    String::try_from_io(pacman(&["-Q"]).output()?.stdout).map(|string| parse_packages(&string))
+}
+
+/// Retrieve the given packages, unless they are not installed.
+pub fn package_versions(packages: &[&str]) -> std::io::Result<Vec<Package>> {
+    String::try_from_io(pacman(&["-Q"]).args(packages).output()?.stdout)
+        .map(|string| parse_packages(&string))
+}
+
+/// Retrieve the available updates of installed packages using `checkupdates`.
+///
+/// This synchronizes temporary copies of the databases,
+/// so that the system's databases are never newer than the installed packages.
+pub fn pending_updates() -> std::io::Result<Vec<Update>> {
+    let output = Command::new(CHECKUPDATES).output()?;
+
+    match output.status.code() {
+        Some(0) => String::try_from_io(output.stdout).map(|string| parse_updates(&string)),
+        // There are no updates.
+        Some(2) => Ok(Vec::new()),
+        _ => Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+/// Upgrade all packages of the system.
+///
+/// Arch Linux does not support partial upgrades, so packages cannot be upgraded selectively.
+pub fn upgrade_system() -> std::io::Result<Output> {
+    let mut command = sudo(UPGRADE);
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+    command.output().map(Output::from)
+}
+
+/// Verify the files of the given package, including their attributes.
+///
+/// This runs unprivileged, so files that are only readable by root are reported as such.
+pub fn verify_files(package: &str) -> std::io::Result<Output> {
+    pacman(&["-Qkk", package]).output().map(Output::from)
+}
+
+/// Determines whether the package database is locked by another pacman process.
+pub fn is_locked() -> bool {
+    Path::new(LOCK_FILE).exists()
+}
+
+/// Parse the output of `pacman -Q`.
+fn parse_packages(text: &str) -> Vec<Package> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Package {
+                name: fields.next()?.to_string(),
+                version: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parse the output of `pacman -Qu`, e.g. `chromium 120.0.6099.224-1 -> 121.0.6167.85-1`.
+fn parse_updates(text: &str) -> Vec<Update> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let installed = fields.next()?;
+
+            if fields.next()? != "->" {
+                return None;
+            }
+
+            Some(Update {
+                name: name.to_string(),
+                installed: installed.to_string(),
+                available: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_packages, parse_updates, Package, Update};
+
+    #[test]
+    fn test_parse_packages() {
+        assert_eq!(
+            parse_packages("chromium 121.0.6167.85-1\ndigsigctl 0.1.20-1\n"),
+            vec![
+                Package {
+                    name: "chromium".into(),
+                    version: "121.0.6167.85-1".into(),
+                },
+                Package {
+                    name: "digsigctl".into(),
+                    version: "0.1.20-1".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_updates() {
+        assert_eq!(
+            parse_updates(
+                "chromium 120.0.6099.224-1 -> 121.0.6167.85-1\n\
+                 linux 6.7.1.arch1-1 -> 6.7.2.arch1-1 [ignored]\n\
+                 :: garbage\n"
+            ),
+            vec![
+                Update {
+                    name: "chromium".into(),
+                    installed: "120.0.6099.224-1".into(),
+                    available: "121.0.6167.85-1".into(),
+                },
+                Update {
+                    name: "linux".into(),
+                    installed: "6.7.1.arch1-1".into(),
+                    available: "6.7.2.arch1-1".into(),
+                },
+            ]
+        );
+    }
+}
@@ -4,12 +4,13 @@
 //! https://portal.homeinfo.de/test.html to fetch configuration URLs based on hostname
 //! and verify if they match the current Chromium startup page.
 
-use crate::config::Config;
+use crate::audit::{self, status_of, Kind};
+use crate::config::{ChromiumPreferences, Config, UrlPolicy};
 use crate::constants::CHROMIUM_SERVICE;
+use crate::history::{self, Change, Source};
 use crate::rpc::default_preferences_file;
-use crate::rpc::operation_mode::activate_exclusive;
+use crate::rpc::operation_mode::{activate_exclusive, OperationMode};
 use anyhow::Result;
-use rocket::serde::json::serde_json;
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -68,7 +69,7 @@ pub async fn verify_startup_page() -> Result<bool> {
     // Get the current Chromium startup URL from preferences
     let startup_url = get_current_startup_url()?;
 
-    Ok(portal_url == startup_url)
+    Ok(Config::new(portal_url).expanded_url()? == startup_url)
 }
 
 /// Get the current Chromium startup URL from preferences
@@ -80,23 +81,10 @@ fn get_current_startup_url() -> Result<String> {
         return Ok(String::new());
     }
 
-    let content = fs::read_to_string(preferences_file)?;
-    let preferences: serde_json::Value = serde_json::from_str(&content)?;
-
-    // Navigate to the startup URLs in the preferences
-    if let Some(session) = preferences.get("session") {
-        if let Some(startup_urls) = session.get("startup_urls") {
-            if let Some(urls) = startup_urls.as_array() {
-                if let Some(first_url) = urls.first() {
-                    if let Some(url) = first_url.as_str() {
-                        return Ok(url.to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(String::new())
+    Ok(ChromiumPreferences::load(preferences_file)?
+        .startup_url()
+        .unwrap_or_default()
+        .to_string())
 }
 
 /// Apply portal configuration on startup only if needed
@@ -106,11 +94,25 @@ pub async fn apply_portal_config_if_needed() -> Result<bool> {
     // Get the current Chromium startup URL from preferences
     let startup_url = get_current_startup_url()?;
 
+    let config = Config::new(portal_url);
+    let portal_url = config.expanded_url()?;
+
     // Only apply configuration if there's a mismatch
     if portal_url != startup_url && portal_url.len() > 0 {
-        let config = Config::new(portal_url);
-        config.apply()?;
-        activate_exclusive(Some(CHROMIUM_SERVICE));
+        // Refuse the portal URL before touching the operation mode.
+        UrlPolicy::load()?.check(&portal_url)?;
+        let result = config.apply();
+        audit::record(Kind::Portal, None, &config, status_of(&result));
+        result?;
+        history::record(Source::Portal, Change::Config(config));
+
+        if activate_exclusive(Some(CHROMIUM_SERVICE)) {
+            history::record(
+                Source::Portal,
+                Change::OperationMode(OperationMode::Chromium),
+            );
+        }
+
         Ok(true) // Configuration was applied
     } else {
         Ok(false) // No configuration needed
@@ -124,7 +126,10 @@ pub async fn apply_portal_config_on_startup() -> Result<()> {
 
     // Create a config with the portal URL and apply it
     let config = Config::new(portal_url);
-    config.apply()?;
+    let result = config.apply();
+    audit::record(Kind::Portal, None, &config, status_of(&result));
+    result?;
+    history::record(Source::Portal, Change::Config(config));
 
     Ok(())
 }
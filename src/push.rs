@@ -0,0 +1,233 @@
+use crate::config::{current_url, Config};
+use crate::identity::{sign, Identity};
+use crate::reconcile::DesiredState;
+use crate::rpc::operation_mode::OperationMode;
+use crate::settings;
+use crate::state;
+use crate::sysinfo::Summary;
+use rocket::serde::json::serde_json;
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Runtime;
+use tracing::{error, info, info_span, warn};
+
+const SECTION: &str = "push";
+const QUEUE_FILE: &str = "push-queue.json";
+const MAX_QUEUED_REPORTS: usize = 100;
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const HOSTNAME_HEADER: &str = "X-Digsigctl-Hostname";
+const MACHINE_ID_HEADER: &str = "X-Digsigctl-Machine-Id";
+const TIMESTAMP_HEADER: &str = "X-Digsigctl-Timestamp";
+const SIGNATURE_HEADER: &str = "X-Digsigctl-Signature";
+
+/// Settings for pushing reports to the portal, read from the `[push]` section of the settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct PushSettings {
+    endpoint: String,
+    interval: Duration,
+    key: Option<Vec<u8>>,
+}
+
+impl PushSettings {
+    /// Loads the push settings.
+    ///
+    /// Returns `None` if no endpoint is configured, i.e. pushing is disabled.
+    fn load() -> Result<Option<Self>, String> {
+        let settings = settings::load()?;
+        let Some(endpoint) = settings.get(SECTION, "endpoint") else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            endpoint,
+            interval: Duration::from_secs(
+                settings
+                    .getuint(SECTION, "interval")?
+                    .unwrap_or(DEFAULT_INTERVAL_SECS),
+            ),
+            key: settings::key(&settings, SECTION)?,
+        }))
+    }
+}
+
+/// Report about the system's state sent to the portal.
+#[derive(Debug, Serialize)]
+struct Report {
+    identity: Identity,
+    time: SystemTime,
+    #[serde(rename = "operationMode")]
+    operation_mode: OperationMode,
+    url: Option<String>,
+    config: Option<Config>,
+    sysinfo: Summary,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            identity: Identity::default(),
+            time: SystemTime::now(),
+            operation_mode: OperationMode::get(),
+            url: current_url(),
+            config: DesiredState::load()
+                .ok()
+                .and_then(|desired_state| desired_state.config().cloned()),
+            sysinfo: Summary::default(),
+        }
+    }
+}
+
+/// Spawns a thread that periodically pushes reports to the portal, if configured.
+///
+/// Reports that could not be delivered are queued on disk and re-sent later.
+pub fn spawn_heartbeat() {
+    let settings = match PushSettings::load() {
+        Ok(Some(settings)) => settings,
+        Ok(None) => return,
+        Err(error) => {
+            error!("Invalid push settings: {error}");
+            return;
+        }
+    };
+
+    let _ = thread::spawn(move || {
+        let _span = info_span!("heartbeat").entered();
+        let runtime = match Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(error) => {
+                error!("Could not start heartbeat runtime: {error}");
+                return;
+            }
+        };
+        let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(error) => {
+                error!("Could not create heartbeat client: {error}");
+                return;
+            }
+        };
+
+        loop {
+            match serde_json::to_string(&Report::default()) {
+                Ok(report) => enqueue(report),
+                Err(error) => error!("Could not serialize report: {error}"),
+            }
+
+            runtime.block_on(flush(&client, &settings));
+            thread::sleep(settings.interval);
+        }
+    });
+}
+
+fn enqueue(report: String) {
+    let mut queue = load_queue();
+    queue.push(report);
+
+    if queue.len() > MAX_QUEUED_REPORTS {
+        let excess = queue.len() - MAX_QUEUED_REPORTS;
+        warn!("Discarding {excess} undelivered reports");
+        queue.drain(..excess);
+    }
+
+    save_queue(&queue);
+}
+
+/// Sends all queued reports, oldest first, until one fails.
+async fn flush(client: &reqwest::Client, settings: &PushSettings) {
+    let mut queue = load_queue();
+    let identity = Identity::default();
+    let mut sent = 0;
+
+    for report in &queue {
+        if let Err(error) = send(client, settings, &identity, report).await {
+            info!("Could not push report, will retry later: {error}");
+            break;
+        }
+
+        sent += 1;
+    }
+
+    if sent > 0 {
+        queue.drain(..sent);
+        save_queue(&queue);
+    }
+}
+
+async fn send(
+    client: &reqwest::Client,
+    settings: &PushSettings,
+    identity: &Identity,
+    report: &str,
+) -> reqwest::Result<()> {
+    let hostname = identity.hostname().unwrap_or_default();
+    let machine_id = identity.machine_id().unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string();
+    let mut request = client
+        .post(settings.endpoint.as_str())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(HOSTNAME_HEADER, hostname)
+        .header(MACHINE_ID_HEADER, machine_id)
+        .header(TIMESTAMP_HEADER, timestamp.as_str());
+
+    if let Some(key) = &settings.key {
+        request = request.header(
+            SIGNATURE_HEADER,
+            format!(
+                "sha256={}",
+                sign(
+                    key,
+                    &signed_payload(hostname, machine_id, &timestamp, report)
+                )
+            ),
+        );
+    }
+
+    request
+        .body(report.to_string())
+        .send()
+        .await?
+        .error_for_status()
+        .map(drop)
+}
+
+/// Returns the payload to sign for a report.
+///
+/// The identity headers and the time of sending are covered by the signature,
+/// so that a captured report can neither be attributed to another system nor be replayed later.
+fn signed_payload(hostname: &str, machine_id: &str, timestamp: &str, report: &str) -> Vec<u8> {
+    format!("{hostname}:{machine_id}:{timestamp}:{report}").into_bytes()
+}
+
+fn load_queue() -> Vec<String> {
+    state::load(QUEUE_FILE)
+        .unwrap_or_else(|error| {
+            error!("Could not read push queue: {error}");
+            None
+        })
+        .unwrap_or_default()
+}
+
+fn save_queue(queue: &[String]) {
+    if let Err(error) = state::save(QUEUE_FILE, queue) {
+        error!("Could not write push queue: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::signed_payload;
+
+    #[test]
+    fn test_signed_payload() {
+        assert_eq!(
+            signed_payload("1234.56", "0123abcd", "1700000000", "{\"a\":1}"),
+            b"1234.56:0123abcd:1700000000:{\"a\":1}".to_vec()
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::audit::{self, Kind};
+use crate::config::confirmation::{self, Outcome};
+use crate::config::{current_url, Config};
+use crate::constants::CONFIGURATION_SERVICE;
+use crate::history::Change;
+use crate::rpc::operation_mode::OperationMode;
+use crate::state;
+use crate::systemctl::is_active;
+use rocket::http::Status;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use subprocess::ExitStatus;
+use tracing::{error, info_span, warn};
+
+const DESIRED_STATE_FILE: &str = "desired.json";
+const MAX_DRIFT_EVENTS: usize = 50;
+
+/// Serializes read-modify-write cycles of the desired state file.
+static LOCK: Mutex<()> = Mutex::new(());
+static DRIFT_EVENTS: Mutex<VecDeque<DriftEvent>> = Mutex::new(VecDeque::new());
+
+/// The state the system shall be in, as last set by the CMS, the portal or a technician.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DesiredState {
+    config: Option<Config>,
+    #[serde(rename = "operationMode")]
+    operation_mode: Option<OperationMode>,
+    #[serde(default, rename = "productiveApplication")]
+    productive_application: Option<OperationMode>,
+}
+
+impl DesiredState {
+    /// Returns the desired configuration, if any.
+    #[must_use]
+    pub const fn config(&self) -> Option<&Config> {
+        self.config.as_ref()
+    }
+
+    /// Returns the explicitly chosen productive application, if any.
+    #[must_use]
+    pub const fn productive_application(&self) -> Option<&OperationMode> {
+        self.productive_application.as_ref()
+    }
+
+    /// Loads the persisted desired state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the desired state could not be read.
+    pub fn load() -> std::io::Result<Self> {
+        state::load(DESIRED_STATE_FILE).map(Option::unwrap_or_default)
+    }
+
+    /// Updates the persisted desired state with the given change.
+    pub fn update(change: &Change) {
+        let _guard = LOCK.lock();
+        let mut desired_state = Self::load().unwrap_or_else(|error| {
+            error!("Could not read desired state: {error}");
+            Self::default()
+        });
+
+        desired_state.apply(change);
+
+        if let Err(error) = state::save(DESIRED_STATE_FILE, &desired_state) {
+            error!("Could not write desired state: {error}");
+        }
+    }
+
+    fn apply(&mut self, change: &Change) {
+        match change {
+            Change::Config(config) => self.config = Some(config.clone()),
+            Change::OperationMode(operation_mode)
+            | Change::ProductiveApplication(operation_mode) => {
+                self.operation_mode = Some(operation_mode.clone());
+
+                // Setting a productive operation mode also chooses its application.
+                if operation_mode.is_productive() {
+                    self.productive_application = Some(operation_mode.clone());
+                }
+            }
+        }
+    }
+}
+
+/// A deviation of the system's actual state from the desired state.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Drift {
+    /// Another operation mode than the desired one is set.
+    #[serde(rename = "operationMode")]
+    OperationMode {
+        expected: OperationMode,
+        actual: OperationMode,
+    },
+    /// The desired operation mode is set, but its service is not running.
+    #[serde(rename = "serviceInactive")]
+    ServiceInactive { service: &'static str },
+    /// Chromium's startup URL differs from the desired one.
+    #[serde(rename = "url")]
+    Url {
+        expected: String,
+        actual: Option<String>,
+    },
+}
+
+/// A detected drift and whether it has been corrected.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct DriftEvent {
+    time: SystemTime,
+    #[serde(flatten)]
+    drift: Drift,
+    corrected: bool,
+    error: Option<String>,
+}
+
+impl DriftEvent {
+    fn new(drift: Drift, result: anyhow::Result<()>) -> Self {
+        Self {
+            time: SystemTime::now(),
+            drift,
+            corrected: result.is_ok(),
+            error: result.err().map(|error| error.to_string()),
+        }
+    }
+
+    /// Returns the time at which the drift was detected.
+    #[must_use]
+    pub const fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Returns the detected drift.
+    #[must_use]
+    pub const fn drift(&self) -> &Drift {
+        &self.drift
+    }
+
+    /// Returns whether the drift has been corrected.
+    #[must_use]
+    pub const fn corrected(&self) -> bool {
+        self.corrected
+    }
+
+    /// Returns the error that prevented the drift from being corrected, if any.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Spawns a thread that periodically reconciles the system with the desired state.
+pub fn spawn_reconciler(interval: Duration) {
+    let _ = thread::spawn(move || {
+        let _span = info_span!("reconciler").entered();
+
+        loop {
+            thread::sleep(interval);
+            reconcile();
+        }
+    });
+}
+
+/// Returns the recently detected drift events, oldest first.
+pub fn drift_events() -> Vec<DriftEvent> {
+    DRIFT_EVENTS
+        .lock()
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Compares the system with the desired state once and corrects any drift.
+pub fn reconcile() {
+    // Do not interfere with technicians setting up the system.
+    if is_active(CONFIGURATION_SERVICE).is_ok_and(|status| status == ExitStatus::Exited(0)) {
+        return;
+    }
+
+    let desired_state = match DesiredState::load() {
+        Ok(desired_state) => desired_state,
+        Err(error) => {
+            error!("Could not read desired state: {error}");
+            return;
+        }
+    };
+
+    if let Some(operation_mode) = &desired_state.operation_mode {
+        reconcile_operation_mode(operation_mode);
+    }
+
+    // A configuration awaiting confirmation must not be overridden.
+    if matches!(confirmation::outcome(), Some(Outcome::Pending { .. })) {
+        return;
+    }
+
+    // Applying a configuration starts Chromium, which is only desired in Chromium mode.
+    if OperationMode::get() != OperationMode::Chromium {
+        return;
+    }
+
+    if let Some(config) = &desired_state.config {
+        reconcile_config(config);
+    }
+}
+
+fn reconcile_operation_mode(operation_mode: &OperationMode) {
+    let actual = OperationMode::get();
+
+    let drift = if actual != *operation_mode {
+        Drift::OperationMode {
+            expected: operation_mode.clone(),
+            actual,
+        }
+    } else if let Some(service) = operation_mode
+        .service()
+        .filter(|service| !is_active(service).is_ok_and(|status| status == ExitStatus::Exited(0)))
+    {
+        Drift::ServiceInactive { service }
+    } else {
+        return;
+    };
+
+    let result = if operation_mode.set() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Could not set operation mode."))
+    };
+    report(DriftEvent::new(drift, result));
+}
+
+fn reconcile_config(config: &Config) {
+    let expected = match config.expanded_url() {
+        Ok(url) => url,
+        Err(error) => {
+            error!("Could not expand desired URL: {error}");
+            return;
+        }
+    };
+    let actual = current_url();
+
+    if actual.as_deref() == Some(expected.as_str()) {
+        return;
+    }
+
+    let result = config.apply();
+    report(DriftEvent::new(Drift::Url { expected, actual }, result));
+}
+
+fn report(event: DriftEvent) {
+    warn!("Detected drift from desired state: {event:?}");
+    audit::record(
+        Kind::Scheduled,
+        None,
+        &event,
+        if event.corrected {
+            Status::Ok
+        } else {
+            Status::InternalServerError
+        },
+    );
+
+    if let Ok(mut events) = DRIFT_EVENTS.lock() {
+        events.push_back(event);
+
+        while events.len() > MAX_DRIFT_EVENTS {
+            events.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DesiredState;
+    use crate::history::Change;
+    use crate::rpc::operation_mode::OperationMode;
+
+    #[test]
+    fn test_apply_operation_mode() {
+        let mut desired_state = DesiredState::default();
+        desired_state.apply(&Change::ProductiveApplication(OperationMode::Html));
+        assert_eq!(desired_state.operation_mode, Some(OperationMode::Html));
+        assert_eq!(
+            desired_state.productive_application,
+            Some(OperationMode::Html)
+        );
+
+        desired_state.apply(&Change::OperationMode(OperationMode::Chromium));
+        assert_eq!(desired_state.operation_mode, Some(OperationMode::Chromium));
+        assert_eq!(
+            desired_state.productive_application,
+            Some(OperationMode::Chromium)
+        );
+
+        desired_state.apply(&Change::OperationMode(OperationMode::BlackScreen));
+        assert_eq!(
+            desired_state.operation_mode,
+            Some(OperationMode::BlackScreen)
+        );
+        assert_eq!(
+            desired_state.productive_application,
+            Some(OperationMode::Chromium)
+        );
+    }
+}
@@ -1,22 +1,122 @@
+mod batch;
 mod beep;
 pub mod chromium;
 mod identify;
 pub(crate) mod operation_mode;
+mod packages;
+mod productive_application;
 mod reboot;
 mod result;
 
+use crate::audit;
+use crate::config::{confirmation, UrlPolicy};
+use crate::history::{self, Change, Source};
+use crate::integrity::Verification;
+use crate::jobs::Context;
+use crate::pacman::{Package, Update};
+pub use batch::{Batch, BatchResult};
 use beep::beep;
 pub use chromium::default_preferences_file;
 use identify::identify;
-use operation_mode::OperationMode;
+use operation_mode::{OperationMode, OperationState};
+use packages::UpgradeReport;
 use reboot::reboot;
 pub use result::Result;
-use serde::Deserialize;
+use rocket::serde::json::{serde_json, Value};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::Debug;
 use std::time::Duration;
 
+/// A request to the RPC server.
+///
+/// This is either a single [`Command`] or a [`Batch`] of commands,
+/// which may also be given as a plain array of commands.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Request {
+    /// A single command.
+    Single(Command),
+    /// An array of commands, which are all executed regardless of errors.
+    Commands(Vec<Command>),
+    /// A batch of commands with options.
+    Batch(Batch),
+}
+
+impl Request {
+    /// Returns the name of the requested command or `batch` for multiple commands.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match self {
+            Self::Single(command) => command.name(),
+            Self::Commands(_) | Self::Batch(_) => "batch".to_string(),
+        }
+    }
+
+    /// Determines whether the request can be cancelled when running as a job.
+    #[must_use]
+    pub const fn is_cancellable(&self) -> bool {
+        matches!(
+            self,
+            Self::Commands(_) | Self::Batch(_) | Self::Single(Command::VerifyPackages(_))
+        )
+    }
+
+    /// Determines whether the request must run as a background job due to its duration.
+    ///
+    /// This is the case if any of the requested commands requires it.
+    #[must_use]
+    pub fn requires_background(&self) -> bool {
+        match self {
+            Self::Single(command) => command.requires_background(),
+            Self::Commands(commands) => commands.iter().any(Command::requires_background),
+            Self::Batch(batch) => batch.commands().iter().any(Command::requires_background),
+        }
+    }
+
+    /// Runs the requested command or commands.
+    ///
+    /// See [`Command::run`] and [`Batch::run`] for details.
+    #[must_use]
+    pub fn run(&self) -> Result {
+        self.run_with(&Context::default())
+    }
+
+    /// Runs the requested command or commands within the context of a job.
+    #[must_use]
+    pub fn run_with(&self, context: &Context) -> Result {
+        match self {
+            Self::Single(command) => command.run_with(context),
+            Self::Commands(commands) => Batch::new(commands.clone(), false).run_with(context),
+            Self::Batch(batch) => batch.run_with(context),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Request {
+    /// Deserializes the request depending on its shape.
+    ///
+    /// Unlike an untagged deserialization, this retains the specific error of a malformed command.
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+
+        match &value {
+            Value::Array(_) => serde_json::from_value(value).map(Self::Commands),
+            Value::Object(object) if object.contains_key("commands") => {
+                serde_json::from_value(value).map(Self::Batch)
+            }
+            _ => serde_json::from_value(value).map(Self::Single),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
 /// Available RPC commands.
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub enum Command {
     /// Beep the PC speaker of the system.
     ///
@@ -51,20 +151,165 @@ pub enum Command {
     ///
     /// If this is `None` it will query and return information
     /// about the current operation mode of the system.
+    /// The response stays a plain [`OperationMode`] for compatibility with existing clients,
+    /// so whether the services are consistent is only reported by `operationState`.
     ///
     /// If this is `Some(OperationMode)` it will set the system to the provided operation mode.
     /// See [`OperationMode`] for further details.
     #[serde(rename = "operationMode")]
     OperationMode(Option<OperationMode>),
+    /// This will return the detailed state of the services of all operation modes,
+    /// including any inconsistencies between them.
+    #[serde(rename = "operationState")]
+    OperationState,
+    /// This will return the active [`UrlPolicy`].
+    ///
+    /// The policy determines which URLs may be configured as startup page.
+    #[serde(rename = "urlPolicy")]
+    UrlPolicy,
+    /// Confirm the configuration that was applied with a confirmation timeout.
+    ///
+    /// Unless confirmed in time, such a configuration will be rolled back automatically.
+    #[serde(rename = "confirmConfig")]
+    ConfirmConfig,
+    /// This will return the outcome of the latest configuration
+    /// that was applied with a confirmation timeout.
+    #[serde(rename = "configConfirmation")]
+    ConfigConfirmation,
+    /// This will return the history of applied configurations and operation modes,
+    /// newest entry first.
+    #[serde(rename = "configHistory")]
+    ConfigHistory,
+    /// Re-apply the given entry of the history.
+    ///
+    /// The entries are indexed as returned by [`Command::ConfigHistory`],
+    /// i.e. `0` is the latest change and `1` the one before.
+    #[serde(rename = "revertConfig")]
+    RevertConfig(usize),
+    /// This will return the entries of the audit log.
+    ///
+    /// If `None` is passed, all entries will be returned.
+    /// Otherwise, only the entries matching the [`audit::Filter`] will be returned.
+    #[serde(rename = "auditLog")]
+    AuditLog(Option<audit::Filter>),
+    /// This will return the installed packages.
+    #[serde(rename = "packages")]
+    Packages,
+    /// Return the available updates.
+    ///
+    /// The updates are determined against temporary copies of the package databases.
+    #[serde(rename = "packageUpdates")]
+    PackageUpdates,
+    /// Upgrade the system, if any of the given packages has an update.
+    ///
+    /// If `None` is passed, all installed packages that may be upgraded are considered.
+    /// Only packages configured as upgradable in the settings may be given.
+    /// Since Arch Linux does not support partial upgrades, all packages are upgraded.
+    /// This always runs as a background job.
+    #[serde(rename = "upgradePackages")]
+    UpgradePackages(Option<Vec<String>>),
+    /// Verify the files of the given packages and store the result for the system information.
+    ///
+    /// If `None` is passed, the packages configured in the settings will be verified.
+    /// This always runs as a background job, which may be cancelled.
+    #[serde(rename = "verifyPackages")]
+    VerifyPackages(Option<Vec<String>>),
+    /// Get or set the productive application, i.e. `chromium`, `html` or `air`.
+    ///
+    /// If this is `None` it will return the preferred productive application, i.e. the one chosen
+    /// last, if it is still installed, or else the first installed one.
+    ///
+    /// If this is `Some(OperationMode)` it will activate the given application exclusively,
+    /// provided that its unit is installed. The choice persists across reboots.
+    #[serde(rename = "productiveApplication")]
+    ProductiveApplication(Option<OperationMode>),
 }
 
 impl Command {
+    /// Names of all available commands.
+    pub const NAMES: [&'static str; 18] = [
+        "beep",
+        "reboot",
+        "identify",
+        "configFile",
+        "restartWebBrowser",
+        "operationMode",
+        "operationState",
+        "urlPolicy",
+        "confirmConfig",
+        "configConfirmation",
+        "configHistory",
+        "revertConfig",
+        "auditLog",
+        "packages",
+        "packageUpdates",
+        "upgradePackages",
+        "verifyPackages",
+        "productiveApplication",
+    ];
+
+    /// Returns the JSON schema of the results of successful commands.
+    ///
+    /// Commands without a result return `null` and those that only report success a message.
+    pub fn results_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "anyOf": [
+                {"type": ["string", "null"]},
+                generator.subschema_for::<OperationMode>(),
+                generator.subschema_for::<OperationState>(),
+                generator.subschema_for::<UrlPolicy>(),
+                generator.subschema_for::<Option<confirmation::Outcome>>(),
+                generator.subschema_for::<Vec<history::Entry>>(),
+                generator.subschema_for::<Change>(),
+                generator.subschema_for::<Vec<audit::Entry>>(),
+                generator.subschema_for::<Vec<Package>>(),
+                generator.subschema_for::<Vec<Update>>(),
+                generator.subschema_for::<UpgradeReport>(),
+                generator.subschema_for::<Verification>(),
+            ],
+        })
+    }
+
+    /// Determines whether the command must run as a background job due to its duration.
+    #[must_use]
+    pub const fn requires_background(&self) -> bool {
+        matches!(self, Self::UpgradePackages(_) | Self::VerifyPackages(_))
+    }
+
+    /// Returns the name of the command as used in its JSON representation.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(Value::String(name)) => name,
+            Ok(Value::Object(object)) => object.keys().next().cloned().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
     /// Runs the RPC command.
     ///
     /// This will return a [`Result`], that will either represent success
     /// or a list of errors that occurred while executing the RPC command.
     #[must_use]
     pub fn run(&self) -> Result {
+        self.run_with(&Context::default())
+    }
+
+    /// Runs the command within the context of a job.
+    #[must_use]
+    pub fn run_with(&self, context: &Context) -> Result {
+        self.execute(Source::Rpc, context)
+    }
+
+    /// Runs the command on behalf of the given source.
+    ///
+    /// The source is recorded in the history of changes made by the command.
+    #[must_use]
+    pub fn run_as(&self, source: Source) -> Result {
+        self.execute(source, &Context::default())
+    }
+
+    fn execute(&self, source: Source, context: &Context) -> Result {
         match self {
             Self::Beep => beep(None),
             Self::Reboot(delay) => reboot(delay.map(Duration::from_secs)),
@@ -83,12 +328,112 @@ impl Command {
                 || Result::Success(Box::new(OperationMode::get())),
                 |operation_mode| {
                     if operation_mode.set() {
+                        history::record(source, Change::OperationMode(operation_mode.clone()));
                         Result::Success(Box::new("Operation mode set"))
                     } else {
                         Result::Error("Could not set operation mode.".into())
                     }
                 },
             ),
+            Self::OperationState => Result::Success(Box::new(OperationState::get())),
+            Self::UrlPolicy => UrlPolicy::load()
+                .map_or_else(Into::into, |policy| Result::Success(Box::new(policy))),
+            Self::ConfirmConfig => confirmation::confirm()
+                .map_or_else(Into::into, |outcome| Result::Success(Box::new(outcome))),
+            Self::ConfigConfirmation => Result::Success(Box::new(confirmation::outcome())),
+            Self::ConfigHistory => history::entries()
+                .map_or_else(Into::into, |entries| Result::Success(Box::new(entries))),
+            Self::RevertConfig(index) => history::revert(*index, source).map_or_else(
+                |error| Result::Error(error.to_string().into()),
+                |change| Result::Success(Box::new(change)),
+            ),
+            Self::AuditLog(filter) => audit::query(&filter.clone().unwrap_or_default())
+                .map_or_else(Into::into, |entries| Result::Success(Box::new(entries))),
+            Self::Packages => packages::list(),
+            Self::PackageUpdates => packages::check_updates(),
+            Self::UpgradePackages(names) => packages::upgrade(names.as_deref(), context),
+            Self::VerifyPackages(names) => packages::verify(names.as_deref(), context),
+            Self::ProductiveApplication(application) => application
+                .as_ref()
+                .map_or_else(productive_application::get, |application| {
+                    productive_application::set(application, source)
+                }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Batch, Command, Request};
+    use rocket::serde::json::serde_json;
+    use schemars::schema_for;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"reboot": 10}"#).unwrap(),
+            Request::Single(Command::Reboot(Some(10)))
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"["restartWebBrowser", "identify"]"#).unwrap(),
+            Request::Commands(vec![Command::RestartWebBrowser, Command::Identify])
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(
+                r#"{"commands": ["beep", {"revertConfig": 1}], "stopOnError": true}"#
+            )
+            .unwrap(),
+            Request::Batch(Batch::new(
+                vec![Command::Beep, Command::RevertConfig(1)],
+                true
+            ))
+        );
+    }
+
+    #[test]
+    fn test_requires_background() {
+        assert!(!Request::Single(Command::Beep).requires_background());
+        assert!(Request::Single(Command::UpgradePackages(None)).requires_background());
+        assert!(
+            Request::Commands(vec![Command::Beep, Command::VerifyPackages(None)])
+                .requires_background()
+        );
+        assert!(Request::Batch(Batch::new(
+            vec![Command::UpgradePackages(None), Command::Reboot(None)],
+            true
+        ))
+        .requires_background());
+    }
+
+    #[test]
+    fn test_names_cover_all_commands() {
+        let schema = serde_json::to_value(schema_for!(Command)).unwrap();
+        let mut names: Vec<&str> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                variant["const"]
+                    .as_str()
+                    .or_else(|| variant["required"][0].as_str())
+                    .unwrap()
+            })
+            .collect();
+        let mut expected = Command::NAMES.to_vec();
+        names.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_parse_malformed_request() {
+        let error = serde_json::from_str::<Request>(r#"{"reboot": "soon"}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("invalid type: string \"soon\""), "{error}");
+        let error = serde_json::from_str::<Request>(r#""shutdown""#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown variant `shutdown`"), "{error}");
+    }
+}
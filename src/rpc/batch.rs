@@ -0,0 +1,120 @@
+use crate::jobs::Context;
+use crate::rpc::result::Error;
+use crate::rpc::{Command, Result};
+use rocket::serde::json::Value;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A sequence of RPC commands to be executed in order.
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Batch {
+    commands: Vec<Command>,
+    /// Skip the remaining commands after the first one that failed.
+    #[serde(default, rename = "stopOnError")]
+    stop_on_error: bool,
+}
+
+impl Batch {
+    /// Creates a new batch of commands.
+    #[must_use]
+    pub const fn new(commands: Vec<Command>, stop_on_error: bool) -> Self {
+        Self {
+            commands,
+            stop_on_error,
+        }
+    }
+
+    /// Returns the commands of the batch.
+    #[must_use]
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Runs the commands in order.
+    ///
+    /// The returned [`Result`] contains the results of the individual commands as well as
+    /// their aggregated status and errors.
+    /// It is successful if all commands succeeded and a [`Result::MultiStatus`] otherwise.
+    #[must_use]
+    pub fn run(&self) -> Result {
+        self.run_with(&Context::default())
+    }
+
+    /// Runs the commands in order within the context of a job.
+    ///
+    /// The remaining commands are skipped, if the job is cancelled.
+    #[must_use]
+    pub fn run_with(&self, context: &Context) -> Result {
+        let mut aggregate = Result::Success(Box::new(()));
+        let mut steps = Vec::with_capacity(self.commands.len());
+
+        for (index, command) in self.commands.iter().enumerate() {
+            if context.is_cancelled() {
+                break;
+            }
+
+            context.progress(format!(
+                "Running command {} of {}: {}",
+                index + 1,
+                self.commands.len(),
+                command.name()
+            ));
+            let result = command.run_with(context);
+            let failed = matches!(result, Result::Error(_));
+            steps.push(Step::new(command, &result));
+            aggregate = aggregate + result;
+
+            if failed && self.stop_on_error {
+                break;
+            }
+        }
+
+        let errors = match &aggregate {
+            Result::Error(errors) => errors.errors().clone(),
+            Result::Success(_) | Result::MultiStatus(_) => Vec::new(),
+        };
+        let batch_result = Box::new(BatchResult {
+            status: aggregate.status().code,
+            skipped: self.commands.len() - steps.len(),
+            results: steps,
+            errors,
+        });
+
+        if batch_result.errors.is_empty() {
+            Result::Success(batch_result)
+        } else {
+            Result::MultiStatus(batch_result)
+        }
+    }
+}
+
+/// Result of a batch of commands.
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct BatchResult {
+    /// Aggregated status of all executed commands.
+    status: u16,
+    /// Amount of commands that have not been executed due to a previous error or cancellation.
+    skipped: usize,
+    results: Vec<Step>,
+    /// Errors of all failed commands.
+    errors: Vec<Error>,
+}
+
+/// Result of a single command within a batch.
+#[derive(Debug, JsonSchema, Serialize)]
+struct Step {
+    command: String,
+    status: u16,
+    /// The command's result or errors.
+    result: Value,
+}
+
+impl Step {
+    fn new(command: &Command, result: &Result) -> Self {
+        Self {
+            command: command.name(),
+            status: result.status().code,
+            result: result.to_value().unwrap_or(Value::Null),
+        }
+    }
+}
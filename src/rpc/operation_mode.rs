@@ -1,21 +1,34 @@
 use crate::constants::{
-    CHROMIUM_SERVICE, CONFLICTING_SERVICES, INSTALLATION_INSTRUCTIONS_SERVICE,
-    UNCONFIGURED_WARNING_SERVICE,
+    AIR_SERVICE, CHROMIUM_SERVICE, CONFLICTING_SERVICES, HTML_SERVICE,
+    INSTALLATION_INSTRUCTIONS_SERVICE, SERVICES_DIR, UNCONFIGURED_WARNING_SERVICE,
 };
+use crate::reconcile::DesiredState;
 use crate::systemctl::{enable_and_start, is_enabled_or_active, stop_and_disable};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use subprocess::ExitStatus;
 
+mod state;
+
+pub use state::{Inconsistency, OperationState, ServiceState};
+
 /// Operation mode of the system.
 ///
 /// This determines what will be shown on the system's display.
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub enum OperationMode {
     /// The Chromium web browser will be started.
     ///
     /// This is used for normal digital signage operations.
     #[serde(rename = "chromium")]
     Chromium,
+    /// The HTML5 digital signage application will be started.
+    #[serde(rename = "html")]
+    Html,
+    /// The AIR digital signage application will be started.
+    #[serde(rename = "air")]
+    Air,
     /// Installation instructions will be shown on the screen.
     ///
     /// This is the default for systems that have not yet been set to production mode.
@@ -34,28 +47,111 @@ pub enum OperationMode {
 }
 
 impl OperationMode {
+    /// All available operation modes.
+    ///
+    /// When determining the current operation mode, the services are checked in this order.
+    /// Modes are only ever appended, so that existing clients keep receiving the modes they know.
+    pub const ALL: [Self; 6] = [
+        Self::Chromium,
+        Self::InstallationInstructions,
+        Self::UnconfiguredWarning,
+        Self::BlackScreen,
+        Self::Html,
+        Self::Air,
+    ];
+
+    /// All available operation modes in order of preference of their applications.
+    ///
+    /// This is the order in which applications are reported in the system information
+    /// and chosen as productive application.
+    pub const PRECEDENCE: [Self; 6] = [
+        Self::Html,
+        Self::Air,
+        Self::Chromium,
+        Self::UnconfiguredWarning,
+        Self::InstallationInstructions,
+        Self::BlackScreen,
+    ];
+
+    /// Returns the current operation mode of the system.
+    #[must_use]
     pub fn get() -> Self {
-        if is_enabled_or_active(CHROMIUM_SERVICE) {
-            Self::Chromium
-        } else if is_enabled_or_active(INSTALLATION_INSTRUCTIONS_SERVICE) {
-            Self::InstallationInstructions
-        } else if is_enabled_or_active(UNCONFIGURED_WARNING_SERVICE) {
-            Self::UnconfiguredWarning
-        } else {
-            Self::BlackScreen
-        }
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.service().is_some_and(is_enabled_or_active))
+            .unwrap_or(Self::BlackScreen)
     }
 
+    /// Returns the productive operation mode to use.
+    ///
+    /// This is the productive application chosen via RPC, if it is still installed,
+    /// or else the first productive operation mode, which is installed on the system.
+    #[must_use]
+    pub fn preferred() -> Option<Self> {
+        DesiredState::load()
+            .ok()
+            .and_then(|desired_state| desired_state.productive_application().cloned())
+            .filter(Self::is_installed)
+            .or_else(|| {
+                Self::PRECEDENCE
+                    .into_iter()
+                    .find(|mode| mode.is_productive() && mode.is_installed())
+            })
+    }
+
+    /// Returns the operation modes, which are installed on the system, in order of preference.
+    #[must_use]
+    pub fn installed() -> Vec<Self> {
+        Self::PRECEDENCE
+            .into_iter()
+            .filter(Self::is_installed)
+            .collect()
+    }
+
+    /// Sets the system to this operation mode.
+    ///
+    /// Returns `true` on success, else `false`.
+    #[must_use]
     pub fn set(&self) -> bool {
+        activate_exclusive(self.service())
+    }
+
+    /// Returns the service that implements this operation mode, if any.
+    #[must_use]
+    pub const fn service(&self) -> Option<&'static str> {
         match self {
-            Self::Chromium => activate_exclusive(Some(CHROMIUM_SERVICE)),
-            Self::InstallationInstructions => {
-                activate_exclusive(Some(INSTALLATION_INSTRUCTIONS_SERVICE))
-            }
-            Self::UnconfiguredWarning => activate_exclusive(Some(UNCONFIGURED_WARNING_SERVICE)),
-            Self::BlackScreen => activate_exclusive(None),
+            Self::Chromium => Some(CHROMIUM_SERVICE),
+            Self::Html => Some(HTML_SERVICE),
+            Self::Air => Some(AIR_SERVICE),
+            Self::InstallationInstructions => Some(INSTALLATION_INSTRUCTIONS_SERVICE),
+            Self::UnconfiguredWarning => Some(UNCONFIGURED_WARNING_SERVICE),
+            Self::BlackScreen => None,
         }
     }
+
+    /// Returns the package that provides the application of this operation mode, if any.
+    #[must_use]
+    pub const fn package(&self) -> Option<&'static str> {
+        match self {
+            Self::Chromium => Some("chromium"),
+            Self::Html => Some("application-html"),
+            Self::Air => Some("application-air"),
+            Self::InstallationInstructions | Self::UnconfiguredWarning | Self::BlackScreen => None,
+        }
+    }
+
+    /// Determines whether this operation mode shows digital signage.
+    #[must_use]
+    pub const fn is_productive(&self) -> bool {
+        matches!(self, Self::Chromium | Self::Html | Self::Air)
+    }
+
+    /// Determines whether the service of this operation mode is installed, if it needs one.
+    #[must_use]
+    pub fn is_installed(&self) -> bool {
+        self.service()
+            .is_none_or(|service| Path::new(SERVICES_DIR).join(service).is_file())
+    }
 }
 
 pub fn activate_exclusive(service: Option<&str>) -> bool {
@@ -67,3 +163,24 @@ pub fn activate_exclusive(service: Option<&str>) -> bool {
         enable_and_start(service).map_or(false, |exit_status| exit_status == ExitStatus::Exited(0))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::OperationMode;
+    use rocket::serde::json::{json, serde_json};
+
+    #[test]
+    fn test_serialization() {
+        assert_eq!(
+            serde_json::to_value(OperationMode::ALL).unwrap(),
+            json!([
+                "chromium",
+                "installationInstructions",
+                "unconfiguredWarning",
+                "blackScreen",
+                "html",
+                "air"
+            ])
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use super::OperationMode;
+use crate::systemctl::show;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::warn;
+
+/// Unit file states for which `systemctl is-enabled` succeeds.
+const ENABLED_STATES: [&str; 7] = [
+    "enabled",
+    "enabled-runtime",
+    "alias",
+    "static",
+    "indirect",
+    "generated",
+    "transient",
+];
+/// Active states for which `systemctl is-active` succeeds.
+const ACTIVE_STATES: [&str; 3] = ["active", "reloading", "refreshing"];
+
+/// Detailed state of the operation mode of the system.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct OperationState {
+    /// The operation mode as returned by the `operationMode` command.
+    #[serde(rename = "operationMode")]
+    operation_mode: OperationMode,
+    consistent: bool,
+    services: Vec<ServiceState>,
+    inconsistencies: Vec<Inconsistency>,
+}
+
+impl OperationState {
+    /// Determines the current state of the services of all operation modes.
+    #[must_use]
+    pub fn get() -> Self {
+        Self::from(
+            OperationMode::ALL
+                .into_iter()
+                .filter_map(ServiceState::get)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Returns the preferred operation mode whose service is both enabled and active.
+    ///
+    /// Unlike the reported `operationMode`, this only considers a service to be in use if it is
+    /// running and will be started after a reboot, as reported in the system information.
+    /// See [`OperationMode::PRECEDENCE`].
+    #[must_use]
+    pub fn running_operation_mode(&self) -> OperationMode {
+        OperationMode::PRECEDENCE
+            .into_iter()
+            .find(|operation_mode| {
+                self.services.iter().any(|service| {
+                    service.operation_mode == *operation_mode && service.enabled && service.active
+                })
+            })
+            .unwrap_or(OperationMode::BlackScreen)
+    }
+
+    /// Returns the operation mode as returned by the `operationMode` command.
+    #[must_use]
+    pub const fn operation_mode(&self) -> &OperationMode {
+        &self.operation_mode
+    }
+
+    /// Returns whether the services of the operation modes are consistent.
+    #[must_use]
+    pub const fn consistent(&self) -> bool {
+        self.consistent
+    }
+
+    /// Returns the states of the services of all operation modes.
+    #[must_use]
+    pub fn services(&self) -> &[ServiceState] {
+        &self.services
+    }
+
+    /// Returns the inconsistencies between the services.
+    #[must_use]
+    pub fn inconsistencies(&self) -> &[Inconsistency] {
+        &self.inconsistencies
+    }
+}
+
+impl From<Vec<ServiceState>> for OperationState {
+    fn from(services: Vec<ServiceState>) -> Self {
+        let inconsistencies = inconsistencies(&services);
+        Self {
+            operation_mode: services
+                .iter()
+                .find(|service| service.enabled || service.active)
+                .map_or(OperationMode::BlackScreen, |service| {
+                    service.operation_mode.clone()
+                }),
+            consistent: inconsistencies.is_empty(),
+            services,
+            inconsistencies,
+        }
+    }
+}
+
+/// State of the service of an operation mode.
+///
+/// A service being enabled reflects the desired state and it being active the actual state.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct ServiceState {
+    service: &'static str,
+    #[serde(rename = "operationMode")]
+    operation_mode: OperationMode,
+    enabled: bool,
+    active: bool,
+    failed: bool,
+    /// The result of the last run of the service, unless it succeeded.
+    #[serde(rename = "failureReason")]
+    failure_reason: Option<String>,
+}
+
+impl ServiceState {
+    fn get(operation_mode: OperationMode) -> Option<Self> {
+        let service = operation_mode.service()?;
+        let properties = show(service, &["UnitFileState", "ActiveState", "Result"])
+            .inspect_err(|error| warn!("Could not query state of {service}: {error}"))
+            .unwrap_or_default();
+        let property = |name: &str| properties.get(name).map(String::as_str);
+        Some(Self {
+            service,
+            operation_mode,
+            enabled: property("UnitFileState").is_some_and(|state| ENABLED_STATES.contains(&state)),
+            active: property("ActiveState").is_some_and(|state| ACTIVE_STATES.contains(&state)),
+            failed: property("ActiveState") == Some("failed"),
+            failure_reason: property("Result")
+                .filter(|result| !result.is_empty() && *result != "success")
+                .map(ToString::to_string),
+        })
+    }
+
+    /// Returns the name of the service.
+    #[must_use]
+    pub const fn service(&self) -> &'static str {
+        self.service
+    }
+
+    /// Returns the operation mode provided by the service.
+    #[must_use]
+    pub const fn operation_mode(&self) -> &OperationMode {
+        &self.operation_mode
+    }
+
+    /// Returns whether the service is enabled.
+    #[must_use]
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns whether the service is active.
+    #[must_use]
+    pub const fn active(&self) -> bool {
+        self.active
+    }
+
+    /// Returns whether the service has failed.
+    #[must_use]
+    pub const fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Returns the result of the last run of the service, unless it succeeded.
+    #[must_use]
+    pub fn failure_reason(&self) -> Option<&str> {
+        self.failure_reason.as_deref()
+    }
+}
+
+/// An inconsistency between the services of the operation modes.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Inconsistency {
+    /// More than one of the mutually exclusive services is enabled.
+    #[serde(rename = "multipleEnabled")]
+    MultipleEnabled { services: Vec<&'static str> },
+    /// More than one of the mutually exclusive services is active.
+    #[serde(rename = "multipleActive")]
+    MultipleActive { services: Vec<&'static str> },
+    /// The service is enabled, but failed.
+    #[serde(rename = "failed")]
+    Failed {
+        service: &'static str,
+        reason: Option<String>,
+    },
+    /// The service is enabled, but not running.
+    #[serde(rename = "enabledButInactive")]
+    EnabledButInactive { service: &'static str },
+    /// The service is running, but not enabled, so it will not be started after a reboot.
+    #[serde(rename = "activeButDisabled")]
+    ActiveButDisabled { service: &'static str },
+}
+
+fn inconsistencies(services: &[ServiceState]) -> Vec<Inconsistency> {
+    let mut inconsistencies = Vec::new();
+    let enabled: Vec<&'static str> = services
+        .iter()
+        .filter(|service| service.enabled)
+        .map(|service| service.service)
+        .collect();
+    let active: Vec<&'static str> = services
+        .iter()
+        .filter(|service| service.active)
+        .map(|service| service.service)
+        .collect();
+
+    if enabled.len() > 1 {
+        inconsistencies.push(Inconsistency::MultipleEnabled { services: enabled });
+    }
+
+    if active.len() > 1 {
+        inconsistencies.push(Inconsistency::MultipleActive { services: active });
+    }
+
+    for service in services {
+        if service.enabled && service.failed {
+            inconsistencies.push(Inconsistency::Failed {
+                service: service.service,
+                reason: service.failure_reason.clone(),
+            });
+        } else if service.enabled && !service.active {
+            inconsistencies.push(Inconsistency::EnabledButInactive {
+                service: service.service,
+            });
+        } else if service.active && !service.enabled {
+            inconsistencies.push(Inconsistency::ActiveButDisabled {
+                service: service.service,
+            });
+        }
+    }
+
+    inconsistencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inconsistency, OperationMode, OperationState, ServiceState};
+
+    fn service(
+        operation_mode: OperationMode,
+        enabled: bool,
+        active: bool,
+        failure_reason: Option<&str>,
+    ) -> ServiceState {
+        ServiceState {
+            service: operation_mode.service().unwrap(),
+            operation_mode,
+            enabled,
+            active,
+            failed: failure_reason.is_some(),
+            failure_reason: failure_reason.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_consistent() {
+        let state = OperationState::from(vec![
+            service(OperationMode::Html, false, false, None),
+            service(OperationMode::Chromium, true, true, None),
+            service(OperationMode::UnconfiguredWarning, false, false, None),
+        ]);
+        assert_eq!(state.operation_mode, OperationMode::Chromium);
+        assert_eq!(state.running_operation_mode(), OperationMode::Chromium);
+        assert!(state.consistent);
+        assert!(state.inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn test_inconsistent() {
+        let state = OperationState::from(vec![
+            service(OperationMode::Chromium, false, true, None),
+            service(OperationMode::UnconfiguredWarning, true, true, None),
+            service(OperationMode::Html, true, false, Some("exit-code")),
+        ]);
+        assert_eq!(state.operation_mode, OperationMode::Chromium);
+        assert_eq!(
+            state.running_operation_mode(),
+            OperationMode::UnconfiguredWarning
+        );
+        assert!(!state.consistent);
+        assert_eq!(
+            state.inconsistencies,
+            vec![
+                Inconsistency::MultipleEnabled {
+                    services: vec!["unconfigured-warning.service", "html5ds.service"],
+                },
+                Inconsistency::MultipleActive {
+                    services: vec!["chromium.service", "unconfigured-warning.service"],
+                },
+                Inconsistency::ActiveButDisabled {
+                    service: "chromium.service",
+                },
+                Inconsistency::Failed {
+                    service: "html5ds.service",
+                    reason: Some("exit-code".into()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Management of the installed software packages.
+
+use super::result::Error;
+use crate::integrity;
+use crate::jobs::Context;
+use crate::pacman::{self, Output, Update};
+use crate::rpc::Result;
+use crate::settings;
+use rocket::http::Status;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::info;
+
+const SECTION: &str = "packages";
+/// Packages that may be upgraded, unless configured otherwise.
+const DEFAULT_UPGRADABLE: [&str; 3] = ["application-html", "digsigctl", "chromium"];
+
+/// Report of a successful package upgrade.
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct UpgradeReport {
+    /// The requested packages, that had updates available.
+    packages: Vec<String>,
+    /// All packages that have been upgraded along with them.
+    updates: Vec<Update>,
+    output: Output,
+}
+
+/// Returns the installed packages.
+pub fn list() -> Result {
+    pacman::installed_packages()
+        .map_or_else(Into::into, |packages| Result::Success(Box::new(packages)))
+}
+
+/// Returns the available updates.
+///
+/// The updates are determined against temporary databases, so that the system's package
+/// databases are never newer than the installed packages.
+pub fn check_updates() -> Result {
+    pacman::pending_updates().map_or_else(Into::into, |updates| Result::Success(Box::new(updates)))
+}
+
+/// Upgrades the system, if any of the given packages or, if `None`,
+/// of the installed upgradable packages has an update.
+///
+/// Only packages configured as upgradable may be requested.
+/// Since partial upgrades are not supported, all packages of the system are upgraded.
+pub fn upgrade(packages: Option<&[String]>, context: &Context) -> Result {
+    let upgradable = match upgradable() {
+        Ok(upgradable) => upgradable,
+        Err(error) => return Result::Error(error.into()),
+    };
+
+    if let Some(package) = packages.and_then(|packages| {
+        packages
+            .iter()
+            .find(|package| !upgradable.contains(package))
+    }) {
+        return Result::Error(
+            (
+                format!("Package may not be upgraded: {package}"),
+                Status::Forbidden,
+            )
+                .into(),
+        );
+    }
+
+    let requested = packages.map_or(upgradable.as_slice(), |packages| packages);
+    context.progress("Checking for updates");
+    let updates = match pacman::pending_updates() {
+        Ok(updates) => updates,
+        Err(error) => return error.into(),
+    };
+    let packages: Vec<String> = updates
+        .iter()
+        .map(|update| update.name().to_string())
+        .filter(|name| requested.contains(name))
+        .collect();
+
+    if packages.is_empty() {
+        return Result::Success(Box::new("No updates available for the requested packages."));
+    }
+
+    if pacman::is_locked() {
+        return Result::Error(
+            (
+                "Package database is locked by another process.".to_string(),
+                Status::Conflict,
+            )
+                .into(),
+        );
+    }
+
+    context.progress(format!("Upgrading system for {}", packages.join(", ")));
+    info!("Upgrading system for packages: {}", packages.join(", "));
+
+    match pacman::upgrade_system() {
+        Ok(output) if output.success() => Result::Success(Box::new(UpgradeReport {
+            packages,
+            updates,
+            output,
+        })),
+        Ok(output) => failed("System upgrade failed.", &output),
+        Err(error) => error.into(),
+    }
+}
+
+/// Verifies the files of the given packages or, if `None`, of the configured packages.
+pub fn verify(packages: Option<&[String]>, context: &Context) -> Result {
+    let packages = match packages {
+        Some(packages) => packages.to_vec(),
+        None => match integrity::configured_packages() {
+            Ok(packages) => packages,
+            Err(error) => return Result::Error(error.into()),
+        },
+    };
+
+    match integrity::verify(&packages, context) {
+        Ok(Some(verification)) => Result::Success(Box::new(verification)),
+        Ok(None) => Result::Error(
+            (
+                "Package verification cancelled.".to_string(),
+                Status::Conflict,
+            )
+                .into(),
+        ),
+        Err(error) => error.into(),
+    }
+}
+
+/// Returns the names of the packages that may be upgraded.
+fn upgradable() -> std::result::Result<Vec<String>, String> {
+    let packages = settings::list(&settings::load()?, SECTION, "upgradable");
+
+    if packages.is_empty() {
+        Ok(DEFAULT_UPGRADABLE.map(String::from).to_vec())
+    } else {
+        Ok(packages)
+    }
+}
+
+fn failed(message: &str, output: &Output) -> Result {
+    Result::Error(
+        (
+            Error::new(
+                Some(message.to_string()),
+                Some(output.stderr().trim().to_string()),
+                output
+                    .exit_code()
+                    .and_then(|exit_code| u32::try_from(exit_code).ok()),
+            ),
+            Status::InternalServerError,
+        )
+            .into(),
+    )
+}
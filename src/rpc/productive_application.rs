@@ -0,0 +1,39 @@
+use crate::history::{self, Change, Source};
+use crate::rpc::operation_mode::OperationMode;
+use crate::rpc::Result;
+use rocket::http::Status;
+
+/// Returns the preferred productive application, if any is installed.
+///
+/// This is the one that is activated when switching to productive mode,
+/// which may differ from the currently active operation mode.
+pub fn get() -> Result {
+    Result::Success(Box::new(OperationMode::preferred()))
+}
+
+/// Activates the given productive application exclusively and persists the choice.
+pub fn set(application: &OperationMode, source: Source) -> Result {
+    if !application.is_productive() {
+        return Result::Error("Not a productive application.".into());
+    }
+
+    if !application.is_installed() {
+        return Result::Error(
+            (
+                format!(
+                    "Unit is not installed: {}",
+                    application.service().unwrap_or_default()
+                ),
+                Status::NotFound,
+            )
+                .into(),
+        );
+    }
+
+    if application.set() {
+        history::record(source, Change::ProductiveApplication(application.clone()));
+        Result::Success(Box::new("Productive application set"))
+    } else {
+        Result::Error("Could not activate productive application.".into())
+    }
+}
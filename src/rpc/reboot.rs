@@ -1,6 +1,7 @@
 use crate::rpc::Result;
 use std::thread;
 use std::time::Duration;
+use tracing::{error, info};
 
 /// Reboots the system.
 ///
@@ -12,7 +13,8 @@ pub fn reboot(delay: Option<Duration>) -> Result {
             thread::sleep(delay);
         }
 
-        system_shutdown::reboot().unwrap_or_else(|error| eprintln!("Could not reboot: {error}"));
+        info!("Rebooting system");
+        system_shutdown::reboot().unwrap_or_else(|error| error!("Could not reboot: {error}"));
     });
 
     Result::Success(Box::new(()))
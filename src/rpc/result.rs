@@ -1,8 +1,10 @@
+pub use error::Error;
 use error::Errors;
 use rocket::http::{ContentType, Status};
 use rocket::response::Responder;
-use rocket::serde::json::serde_json;
+use rocket::serde::json::{serde_json, Value};
 use rocket::{Request, Response};
+use schemars::{Schema, SchemaGenerator};
 use std::io::Cursor;
 use std::ops::Add;
 
@@ -18,6 +20,39 @@ pub enum Result {
     ///
     /// Details about the errors that occurred can be read from the `Errors` struct.
     Error(Errors),
+    /// The RPC call consisted of multiple operations, of which some failed.
+    ///
+    /// The return data describes the outcome of each operation.
+    MultiStatus(Box<dyn erased_serde::Serialize>),
+}
+
+impl Result {
+    /// Returns the HTTP status that represents this result.
+    #[must_use]
+    pub const fn status(&self) -> Status {
+        match self {
+            Self::Success(_) => Status::Ok,
+            Self::Error(errors) => errors.status(),
+            Self::MultiStatus(_) => Status::MultiStatus,
+        }
+    }
+
+    /// Returns the JSON schema of the errors of a failed RPC call.
+    pub fn errors_schema(generator: &mut SchemaGenerator) -> Schema {
+        generator.subschema_for::<Vec<Error>>()
+    }
+
+    /// Serializes the return data or, respectively, the errors of this result.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`serde_json::Error`] if the data cannot be serialized.
+    pub fn to_value(&self) -> serde_json::Result<Value> {
+        match self {
+            Self::Success(value) | Self::MultiStatus(value) => serde_json::to_value(value.as_ref()),
+            Self::Error(errors) => serde_json::to_value(errors.errors()),
+        }
+    }
 }
 
 impl Add for Result {
@@ -51,6 +86,9 @@ impl From<Result> for (Status, String) {
             Result::Error(errors) => {
                 serde_json::to_string(errors.errors()).map(|json| (errors.status(), json))
             }
+            Result::MultiStatus(value) => {
+                serde_json::to_string(value.as_ref()).map(|json| (Status::MultiStatus, json))
+            }
         }
         .unwrap_or((
             Status::InternalServerError,
@@ -1,8 +1,9 @@
 use rocket::http::Status;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::ops::Add;
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct Error {
     message: Option<String>,
     details: Option<String>,
@@ -114,6 +115,12 @@ impl From<(&[Error], Status)> for Errors {
     }
 }
 
+impl From<(String, Status)> for Errors {
+    fn from((message, status): (String, Status)) -> Self {
+        Self::from((Error::from(message), status))
+    }
+}
+
 impl From<&str> for Errors {
     fn from(message: &str) -> Self {
         Self::from(message.to_string())
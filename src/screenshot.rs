@@ -4,9 +4,9 @@ use rocket::{Request, Response};
 use std::io::Cursor;
 #[allow(clippy::module_name_repetitions)]
 #[cfg(target_family = "unix")]
-pub use unix::take_screenshot;
+pub use unix::{is_supported, take_screenshot};
 #[cfg(target_family = "windows")]
-pub use windows::take_screenshot;
+pub use windows::{is_supported, take_screenshot};
 
 /// Response type that contains a screenshot as binary data.
 #[allow(clippy::module_name_repetitions)]
@@ -41,13 +41,20 @@ impl From<anyhow::Result<Vec<u8>>> for ScreenshotResponse {
 
 #[cfg(target_family = "unix")]
 mod unix {
+    use crate::constants::SERVICES_DIR;
     use crate::systemctl;
     use std::fs::read;
+    use std::path::Path;
     use subprocess::ExitStatus;
 
     const SCREENSHOT_SERVICE: &str = "screenshot.service";
     const SCREENSHOT_FILE: &str = "/tmp/screenshot.png";
 
+    /// Determines whether the system supports taking screenshots.
+    pub fn is_supported() -> bool {
+        Path::new(SERVICES_DIR).join(SCREENSHOT_SERVICE).is_file()
+    }
+
     /// Take a screenshot of the running Chromium browser running in Cage
     ///
     /// # Errors
@@ -65,6 +72,10 @@ mod unix {
 
 #[cfg(target_family = "windows")]
 mod windows {
+    pub const fn is_supported() -> bool {
+        false
+    }
+
     pub fn take_screenshot() -> anyhow::Result<Vec<u8>> {
         todo!()
     }
@@ -0,0 +1,52 @@
+use configparser::ini::Ini;
+use std::fs::read;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "/etc/digsigctl.conf";
+
+/// Loads the local settings of `digsigctl`.
+///
+/// If the settings file does not exist, empty settings are returned.
+///
+/// # Errors
+///
+/// This function will return an error message if the settings file could not be parsed.
+pub fn load() -> Result<Ini, String> {
+    let mut settings = Ini::new();
+
+    if Path::new(SETTINGS_FILE).exists() {
+        settings.load(SETTINGS_FILE)?;
+    }
+
+    Ok(settings)
+}
+
+/// Returns the comma-separated values of the given key.
+pub fn list(settings: &Ini, section: &str, key: &str) -> Vec<String> {
+    settings
+        .get(section, key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the key from the file configured as `key_file` in the given section.
+///
+/// Returns `None` if no key file is configured.
+///
+/// # Errors
+///
+/// This function will return an error message if the key file could not be read.
+pub fn key(settings: &Ini, section: &str) -> Result<Option<Vec<u8>>, String> {
+    settings
+        .get(section, "key_file")
+        .map(read)
+        .transpose()
+        .map_err(|error| error.to_string())
+}
@@ -0,0 +1,57 @@
+use rocket::serde::json::serde_json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{create_dir_all, read_to_string, rename, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+/// Directory for persistent state, as provided by systemd's `StateDirectory=`.
+const STATE_DIR: &str = "/var/lib/digsigctl";
+
+/// Returns the path to the given file within the state directory.
+pub fn path(name: &str) -> PathBuf {
+    PathBuf::from(STATE_DIR).join(name)
+}
+
+/// Loads a JSON value from the given file within the state directory.
+///
+/// Returns `None` if the file does not exist.
+///
+/// # Errors
+///
+/// This function will return an [`std::io::Error`] if the file could not be read or deserialized.
+pub fn load<T>(name: &str) -> std::io::Result<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    match read_to_string(path(name)) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Atomically saves a JSON value to the given file within the state directory.
+///
+/// # Errors
+///
+/// This function will return an [`std::io::Error`] if the file could not be serialized or written.
+pub fn save<T>(name: &str, value: &T) -> std::io::Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    let json = serde_json::to_string(value)
+        .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error))?;
+    let filename = path(name);
+    let tempfile = filename.with_extension("tmp");
+    create_dir_all(STATE_DIR)?;
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tempfile)?
+        .write_all(json.as_bytes())?;
+    rename(tempfile, filename)
+}
@@ -1,9 +1,13 @@
-use rocket::log::private::warn;
 use rocket::serde::json::serde_json;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::collections::HashMap;
 use sysinfo::Disks;
+use tracing::warn;
 
+use crate::integrity::{self, Verification};
+use crate::reconcile::{drift_events, DriftEvent};
+use crate::rpc::operation_mode::OperationState;
 use crate::sysinfo::smart::device_states;
 use application::Metadata;
 use cmdline::cmdline;
@@ -12,29 +16,36 @@ use df::Entry;
 use efi::Efi;
 use meminfo::meminfo;
 use mount::root_mounted_ro;
+use network::Network;
+use packages::Packages;
+use reboot_required::Reason;
 use sensors::sensors;
 use uptime::Uptime;
 
-mod application;
+pub mod application;
 mod cmdline;
 mod cpuinfo;
 mod df;
 mod efi;
 mod meminfo;
 mod mount;
-mod sensors;
-mod smart;
+mod network;
+mod packages;
+mod reboot_required;
+pub mod sensors;
+pub mod smart;
 mod uptime;
+pub mod v2;
 
 #[allow(dead_code)]
-#[derive(Debug, Serialize)]
+#[derive(Debug, JsonSchema, Serialize)]
 pub enum Os {
     Unix,
     Windows,
 }
 
 /// Collected information about the local digital signage system the program is running on.
-#[derive(Debug, Serialize)]
+#[derive(Debug, JsonSchema, Serialize)]
 pub struct SystemInformation {
     os: Os,
     application: Metadata,
@@ -51,34 +62,83 @@ pub struct SystemInformation {
     sensors: Option<serde_json::Value>,
     uptime: Uptime,
     smartctl: Option<HashMap<String, Option<String>>>,
+    drift: Vec<DriftEvent>,
+    packages: Packages,
+    reboot_required: bool,
+    reboot_reasons: Vec<Reason>,
+    file_integrity: Option<Verification>,
+    operation_state: OperationState,
+    network: Network,
 }
 
 impl Default for SystemInformation {
     fn default() -> Self {
+        let reboot_reasons = reboot_required::reasons();
+        let operation_state = OperationState::get();
         Self {
             #[cfg(target_family = "unix")]
             os: Os::Unix,
             #[cfg(target_family = "windows")]
             os: Os::Windows,
-            application: application::status(),
+            application: application::status(&operation_state),
             baytrail: CpuInfo::read().map(|cpu_info| cpu_info.is_bay_trail()).ok(),
             efi: Efi::default(),
             cmd_line: cmdline().ok(),
             cpu_info: CpuInfo::read().ok(),
-            df: Disks::new_with_refreshed_list()
-                .list()
-                .iter()
-                .filter_map(|disk| {
-                    Entry::try_from(disk)
-                        .inspect_err(|_| warn!("Invalid entry: {disk:?}"))
-                        .ok()
-                })
-                .collect(),
+            df: disks(),
             mem_info: meminfo().ok(),
             root_ro: root_mounted_ro().ok(),
             sensors: sensors().ok(),
             uptime: Uptime::default(),
             smartctl: device_states().ok(),
+            drift: drift_events(),
+            packages: Packages::default(),
+            reboot_required: !reboot_reasons.is_empty(),
+            reboot_reasons,
+            file_integrity: integrity::latest(),
+            operation_state,
+            network: Network::default(),
         }
     }
 }
+
+/// Compact system information, as periodically pushed to the portal.
+///
+/// The fields are serialized in the same way as in the full [`SystemInformation`].
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    application: Metadata,
+    df: Vec<Entry>,
+    root_ro: Option<bool>,
+    uptime: Uptime,
+    drift: Vec<DriftEvent>,
+    reboot_required: bool,
+    operation_state: OperationState,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        let operation_state = OperationState::get();
+        Self {
+            application: application::status(&operation_state),
+            df: disks(),
+            root_ro: root_mounted_ro().ok(),
+            uptime: Uptime::default(),
+            drift: drift_events(),
+            reboot_required: !reboot_required::reasons().is_empty(),
+            operation_state,
+        }
+    }
+}
+
+fn disks() -> Vec<Entry> {
+    Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter_map(|disk| {
+            Entry::try_from(disk)
+                .inspect_err(|_| warn!("Invalid entry: {disk:?}"))
+                .ok()
+        })
+        .collect()
+}
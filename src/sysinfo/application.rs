@@ -1,24 +1,10 @@
-use std::path::PathBuf;
-
 use crate::pacman::package_version;
-use crate::systemctl::{is_active, is_enabled};
+use crate::rpc::operation_mode::{OperationMode, OperationState};
+use schemars::JsonSchema;
 use serde::Serialize;
-use subprocess::ExitStatus;
-
-const SERVICES_DIR: &str = "/usr/lib/systemd/system";
-/// Preference of the applications.
-///
-/// When determining the application status, the active units will be checked in this order.
-const APPLICATION_PREFERENCE: [Application; 5] = [
-    Application::Html,
-    Application::Air,
-    Application::NotConfiguredWarning,
-    Application::InstallationInstructions,
-    Application::Off,
-];
 
 /// Representation of the application mode in the system information.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
 pub enum Mode {
     /// The system is configured to show the digital signage application.
     #[serde(rename(serialize = "PRODUCTIVE"))]
@@ -36,7 +22,18 @@ pub enum Mode {
     Off,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+impl From<&OperationMode> for Mode {
+    fn from(operation_mode: &OperationMode) -> Self {
+        match operation_mode {
+            OperationMode::Chromium | OperationMode::Html | OperationMode::Air => Self::Productive,
+            OperationMode::InstallationInstructions => Self::InstallationInstructions,
+            OperationMode::UnconfiguredWarning => Self::NotConfigured,
+            OperationMode::BlackScreen => Self::Off,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct Metadata {
     name: &'static str,
     mode: Mode,
@@ -46,106 +43,34 @@ pub struct Metadata {
 }
 
 impl Metadata {
-    pub fn new(
-        name: &'static str,
-        mode: Mode,
-        unit: Option<&'static str>,
-        package: Option<&'static str>,
-    ) -> Self {
-        Self {
-            name,
-            mode,
-            unit,
-            package,
-            version: package.and_then(|package| package_version(package).ok()),
-        }
-    }
-
-    pub fn is_productive(&self) -> bool {
-        self.mode == Mode::Productive
-    }
-}
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum Application {
-    Html,
-    Air,
-    NotConfiguredWarning,
-    InstallationInstructions,
-    Off,
-}
-
-impl From<Application> for Metadata {
-    fn from(application: Application) -> Self {
-        match application {
-            Application::Html => Self::new(
-                "html",
-                Mode::Productive,
-                Some("html5ds.service"),
-                Some("application-html"),
-            ),
-            Application::Air => Self::new(
-                "air",
-                Mode::Productive,
-                Some("application.service"),
-                Some("application-air"),
-            ),
-            Application::NotConfiguredWarning => Self::new(
-                "not configured",
-                Mode::NotConfigured,
-                Some("unconfigured-warning.service"),
-                None,
-            ),
-            Application::InstallationInstructions => Self::new(
-                "installation instructions",
-                Mode::InstallationInstructions,
-                Some("installation-instructions.service"),
-                None,
-            ),
-            Application::Off => Self::new("off", Mode::Off, None, None),
-        }
+    pub const fn name(&self) -> &'static str {
+        self.name
     }
 }
 
-impl From<Mode> for Option<Application> {
-    fn from(mode: Mode) -> Self {
-        match mode {
-            Mode::Productive => get_preferred(),
-            Mode::InstallationInstructions => Some(Application::InstallationInstructions),
-            Mode::NotConfigured => Some(Application::NotConfiguredWarning),
-            Mode::Off => Some(Application::Off),
-        }
-    }
-}
-
-/// Return the first of the preferred applications which is available on the system.
-pub fn get_preferred() -> Option<Application> {
-    for application in APPLICATION_PREFERENCE {
-        let metadata = Metadata::from(application);
-
-        if metadata.is_productive() {
-            if let Some(unit) = metadata.unit {
-                if PathBuf::from(SERVICES_DIR).join(unit).is_file() {
-                    return Some(application);
-                }
-            }
+impl From<OperationMode> for Metadata {
+    fn from(operation_mode: OperationMode) -> Self {
+        let package = operation_mode.package();
+        Self {
+            name: match operation_mode {
+                OperationMode::Chromium => "chromium",
+                OperationMode::Html => "html",
+                OperationMode::Air => "air",
+                OperationMode::InstallationInstructions => "installation instructions",
+                OperationMode::UnconfiguredWarning => "not configured",
+                OperationMode::BlackScreen => "off",
+            },
+            mode: Mode::from(&operation_mode),
+            unit: operation_mode.service(),
+            package,
+            version: package.and_then(|package| package_version(package).ok()),
         }
     }
-
-    None
 }
 
-/// Return the current application status on the system.
-pub fn status() -> Metadata {
-    for metadata in APPLICATION_PREFERENCE.map(Metadata::from) {
-        if let Some(unit) = metadata.unit {
-            if is_enabled(unit).unwrap_or(ExitStatus::Undetermined) == ExitStatus::Exited(0)
-                && is_active(unit).unwrap_or(ExitStatus::Undetermined) == ExitStatus::Exited(0)
-            {
-                return metadata;
-            }
-        }
-    }
-
-    Application::Off.into()
+/// Return the application status for the given operation state of the system.
+///
+/// An application is only reported if its service is both enabled and active.
+pub fn status(operation_state: &OperationState) -> Metadata {
+    operation_state.running_operation_mode().into()
 }
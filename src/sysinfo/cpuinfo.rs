@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Information about the system's CPU.
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct CpuInfo {
     is_bay_trail: bool,
     model_name: Option<String>,
@@ -1,13 +1,14 @@
 use std::num::TryFromIntError;
 use std::path::PathBuf;
 
+use schemars::JsonSchema;
 use serde::Serialize;
 use sysinfo::Disk;
 
 /// Information about free disk space on the system.
 ///
 /// Each `Entry` represents one filesystem on the system.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct Entry {
     filesystem: String,
     used: u64,
@@ -1,12 +1,13 @@
 use std::path::PathBuf;
 
+use schemars::JsonSchema;
 use serde::Serialize;
 use sysinfo::Disks;
 
 const EFI_PARTITION: &str = "/dev/disk/by-label/EFI";
 
 /// Information about the EFI partition.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct Efi {
     mounted: bool,
 }
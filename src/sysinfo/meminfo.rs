@@ -1,7 +1,7 @@
-use rocket::warn;
 use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::path::Path;
+use tracing::warn;
 
 const PROC_MEMINFO: &str = "/proc/meminfo";
 const KIB: usize = 1024; // Kibibytes.
@@ -15,6 +15,17 @@ pub fn meminfo() -> std::io::Result<HashMap<String, usize>> {
     meminfo_from_file(PROC_MEMINFO)
 }
 
+/// Collects a hash map of the entries in `/proc/meminfo`, which are given in bytes.
+///
+/// In contrast to [`meminfo`], this omits unitless counts, such as `HugePages_Total`.
+///
+/// # Errors
+///
+/// This function will return an [`std::io::Error`] if `/proc/meminfo` could not be read.
+pub fn memory_bytes() -> std::io::Result<HashMap<String, usize>> {
+    read_to_string(PROC_MEMINFO).map(memory_bytes_from_text)
+}
+
 fn meminfo_from_file(filename: impl AsRef<Path>) -> std::io::Result<HashMap<String, usize>> {
     read_to_string(filename.as_ref()).map(meminfo_from_text)
 }
@@ -30,6 +41,17 @@ fn meminfo_from_text(text: impl AsRef<str>) -> HashMap<String, usize> {
         .collect()
 }
 
+fn memory_bytes_from_text(text: impl AsRef<str>) -> HashMap<String, usize> {
+    text.as_ref()
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let (value, unit) = value.trim().split_once(' ')?;
+            parse_value_with_unit(value, unit).map(|value| (key.trim().to_string(), value))
+        })
+        .collect()
+}
+
 fn parse_value(value: &str) -> Option<usize> {
     value
         .trim()
@@ -59,7 +81,7 @@ fn parse_unit(unit: &str) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::{meminfo_from_text, KIB};
+    use super::{meminfo_from_text, memory_bytes_from_text, KIB};
 
     const MEMINFO_TEXT: &str = r"MemTotal:        8001412 kB
 MemFree:          136304 kB
@@ -134,4 +156,13 @@ DirectMap1G:     2097152 kB
         assert_eq!(meminfo.get("DirectMap1G").copied(), Some(2_097_152 * KIB));
         assert_eq!(meminfo.get("NoSuchKey").copied(), None);
     }
+
+    #[test]
+    fn test_memory_bytes_from_text() {
+        let memory_bytes = memory_bytes_from_text(MEMINFO_TEXT);
+        assert_eq!(memory_bytes.get("MemFree").copied(), Some(136_304 * KIB));
+        assert_eq!(memory_bytes.get("Hugepagesize").copied(), Some(2048 * KIB));
+        assert_eq!(memory_bytes.get("HugePages_Total").copied(), None);
+        assert_eq!(memory_bytes.get("HugePages_Free").copied(), None);
+    }
 }
@@ -0,0 +1,385 @@
+use crate::net::{listen_addresses, mac_address};
+use crate::sudo::sudo;
+use crate::timestamp::Timestamp;
+use local_ip_address::list_afinet_netifas;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs::{read_dir, read_to_string};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const IPV4_ROUTES: &str = "/proc/net/route";
+const IPV6_ROUTES: &str = "/proc/net/ipv6_route";
+/// Upstream DNS servers, if `systemd-resolved` is used.
+const RESOLVED_RESOLV_CONF: &str = "/run/systemd/resolve/resolv.conf";
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const WG: &str = "/usr/bin/wg";
+/// How long queried `WireGuard` handshakes are reused, since querying them requires `sudo`.
+const WIREGUARD_CACHE_DURATION: Duration = Duration::from_mins(1);
+/// Route flag of routes using a gateway.
+const RTF_GATEWAY: u32 = 0x0002;
+/// Route flag of unreachable routes.
+const RTF_REJECT: u32 = 0x0200;
+/// Prefixes of the interfaces created by `OpenVPN`.
+const OPENVPN_PREFIXES: [&str; 2] = ["tun", "tap"];
+
+static WIREGUARD_CACHE: Mutex<Option<(Instant, Vec<Tunnel>)>> = Mutex::new(None);
+
+/// Network interfaces and connectivity of the system.
+#[derive(Debug, JsonSchema, Serialize)]
+pub struct Network {
+    interfaces: Vec<Interface>,
+    default_routes: Vec<Route>,
+    dns_servers: Vec<IpAddr>,
+    /// The addresses the RPC server listens on.
+    listen_addresses: Vec<IpAddr>,
+    tunnels: Vec<Tunnel>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        let interfaces = interfaces();
+        let tunnels = wireguard_tunnels(&interfaces)
+            .into_iter()
+            .chain(openvpn_tunnels(&interfaces))
+            .collect();
+        Self {
+            interfaces,
+            default_routes: read_to_string(IPV4_ROUTES)
+                .map(|routes| parse_ipv4_default_routes(&routes))
+                .unwrap_or_default()
+                .into_iter()
+                .chain(
+                    read_to_string(IPV6_ROUTES)
+                        .map(|routes| parse_ipv6_default_routes(&routes))
+                        .unwrap_or_default(),
+                )
+                .collect(),
+            dns_servers: read_to_string(RESOLVED_RESOLV_CONF)
+                .or_else(|_| read_to_string(RESOLV_CONF))
+                .map(|resolv_conf| parse_nameservers(&resolv_conf))
+                .unwrap_or_default(),
+            listen_addresses: listen_addresses(),
+            tunnels,
+        }
+    }
+}
+
+/// A network interface.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Interface {
+    name: String,
+    mac: Option<String>,
+    ipv4: Vec<Ipv4Addr>,
+    ipv6: Vec<Ipv6Addr>,
+    /// The operational state as reported by the kernel, e.g. `up` or `down`.
+    state: Option<String>,
+    speed_mbps: Option<u32>,
+}
+
+/// A default route.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Route {
+    interface: String,
+    /// The gateway, unless the route directly uses the interface, e.g. of a tunnel.
+    gateway: Option<IpAddr>,
+}
+
+/// A VPN tunnel.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Tunnel {
+    /// A peer of a `WireGuard` interface.
+    #[serde(rename = "wireguard")]
+    WireGuard {
+        interface: String,
+        peer: String,
+        /// The time of the latest handshake, if any has taken place.
+        latest_handshake: Option<Timestamp>,
+    },
+    /// An interface as created by `OpenVPN`.
+    #[serde(rename = "openvpn")]
+    OpenVpn {
+        interface: String,
+        state: Option<String>,
+    },
+}
+
+fn interfaces() -> Vec<Interface> {
+    let addresses = list_afinet_netifas().unwrap_or_default();
+    let mut interfaces: Vec<Interface> = read_dir(SYS_CLASS_NET)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .map(|name| {
+            let dir = PathBuf::from(SYS_CLASS_NET).join(&name);
+            let addresses = addresses
+                .iter()
+                .filter(|(interface, _)| *interface == name)
+                .map(|(_, address)| *address);
+            Interface {
+                mac: mac_address(&name).ok().filter(|mac| !mac.is_empty()),
+                ipv4: addresses
+                    .clone()
+                    .filter_map(|address| match address {
+                        IpAddr::V4(address) => Some(address),
+                        IpAddr::V6(_) => None,
+                    })
+                    .collect(),
+                ipv6: addresses
+                    .filter_map(|address| match address {
+                        IpAddr::V4(_) => None,
+                        IpAddr::V6(address) => Some(address),
+                    })
+                    .collect(),
+                state: read_attribute(&dir, "operstate"),
+                // The speed is -1 or cannot be read, if the link is down.
+                speed_mbps: read_attribute(&dir, "speed").and_then(|speed| speed.parse().ok()),
+                name,
+            }
+        })
+        .collect();
+    interfaces.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    interfaces
+}
+
+fn read_attribute(dir: &Path, attribute: &str) -> Option<String> {
+    read_to_string(dir.join(attribute))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+fn wireguard_tunnels(interfaces: &[Interface]) -> Vec<Tunnel> {
+    if !Path::new(WG).is_file()
+        || !interfaces
+            .iter()
+            .any(|interface| is_wireguard(&interface.name))
+    {
+        return Vec::new();
+    }
+
+    let Ok(mut cache) = WIREGUARD_CACHE.lock() else {
+        return latest_handshakes();
+    };
+
+    match cache.as_ref() {
+        Some((queried_at, tunnels)) if queried_at.elapsed() < WIREGUARD_CACHE_DURATION => {
+            tunnels.clone()
+        }
+        _ => {
+            let tunnels = latest_handshakes();
+            cache.replace((Instant::now(), tunnels.clone()));
+            tunnels
+        }
+    }
+}
+
+fn is_wireguard(interface: &str) -> bool {
+    read_to_string(PathBuf::from(SYS_CLASS_NET).join(interface).join("uevent"))
+        .is_ok_and(|uevent| uevent.lines().any(|line| line == "DEVTYPE=wireguard"))
+}
+
+fn latest_handshakes() -> Vec<Tunnel> {
+    let mut command = sudo(WG);
+    command.args(["show", "all", "latest-handshakes"]);
+    command
+        .output()
+        .map(|output| parse_latest_handshakes(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+fn openvpn_tunnels(interfaces: &[Interface]) -> Vec<Tunnel> {
+    interfaces
+        .iter()
+        .filter(|interface| {
+            OPENVPN_PREFIXES
+                .iter()
+                .any(|prefix| interface.name.starts_with(prefix))
+        })
+        .map(|interface| Tunnel::OpenVpn {
+            interface: interface.name.clone(),
+            state: interface.state.clone(),
+        })
+        .collect()
+}
+
+/// Parse the default routes from `/proc/net/route`.
+fn parse_ipv4_default_routes(routes: &str) -> Vec<Route> {
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (interface, destination, gateway, flags, mask) = (
+                fields.first()?,
+                fields.get(1)?,
+                fields.get(2)?,
+                u32::from_str_radix(fields.get(3)?, 16).ok()?,
+                fields.get(7)?,
+            );
+
+            if *destination != "00000000" || *mask != "00000000" || flags & RTF_REJECT != 0 {
+                return None;
+            }
+
+            // The address is printed as a number, whose bytes in memory are in network order.
+            let gateway = u32::from_str_radix(gateway, 16).ok()?.to_ne_bytes();
+            Some(Route {
+                interface: (*interface).to_string(),
+                gateway: (flags & RTF_GATEWAY != 0).then(|| IpAddr::V4(Ipv4Addr::from(gateway))),
+            })
+        })
+        .collect()
+}
+
+/// Parse the default routes from `/proc/net/ipv6_route`.
+fn parse_ipv6_default_routes(routes: &str) -> Vec<Route> {
+    routes
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (destination, prefix, gateway, flags, interface) = (
+                fields.first()?,
+                fields.get(1)?,
+                fields.get(4)?,
+                u32::from_str_radix(fields.get(8)?, 16).ok()?,
+                fields.get(9)?,
+            );
+
+            if destination.chars().any(|char| char != '0')
+                || *prefix != "00"
+                || flags & RTF_REJECT != 0
+            {
+                return None;
+            }
+
+            let gateway = u128::from_str_radix(gateway, 16).ok()?;
+            Some(Route {
+                interface: (*interface).to_string(),
+                gateway: (flags & RTF_GATEWAY != 0).then(|| IpAddr::V6(Ipv6Addr::from(gateway))),
+            })
+        })
+        .collect()
+}
+
+/// Parse the name servers from a `resolv.conf` file.
+fn parse_nameservers(resolv_conf: &str) -> Vec<IpAddr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|address| address.trim().parse().ok())
+        .collect()
+}
+
+/// Parse the output of `wg show all latest-handshakes`.
+fn parse_latest_handshakes(output: &str) -> Vec<Tunnel> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let interface = fields.next()?;
+            let peer = fields.next()?;
+            let seconds: u64 = fields.next()?.parse().ok()?;
+            Some(Tunnel::WireGuard {
+                interface: interface.to_string(),
+                peer: peer.to_string(),
+                latest_handshake: (seconds > 0)
+                    .then(|| (UNIX_EPOCH + Duration::from_secs(seconds)).into()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_ipv4_default_routes, parse_ipv6_default_routes, parse_latest_handshakes,
+        parse_nameservers, Route, Tunnel,
+    };
+    use std::net::IpAddr;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_parse_ipv4_default_routes() {
+        let routes = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0100A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0000A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+tun0\t00000000\t00000000\t0001\t0\t0\t50\t00000000\t0\t0\t0
+";
+        assert_eq!(
+            parse_ipv4_default_routes(routes),
+            vec![
+                Route {
+                    interface: "eth0".into(),
+                    gateway: Some("192.168.0.1".parse().unwrap()),
+                },
+                Route {
+                    interface: "tun0".into(),
+                    gateway: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_default_routes() {
+        let routes = "\
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003 eth0
+fd561dda8794cb900000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001 wg0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 00000400 00000001 00000000 00000001 wg0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo
+";
+        assert_eq!(
+            parse_ipv6_default_routes(routes),
+            vec![
+                Route {
+                    interface: "eth0".into(),
+                    gateway: Some("fe80::1".parse().unwrap()),
+                },
+                Route {
+                    interface: "wg0".into(),
+                    gateway: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_nameservers() {
+        assert_eq!(
+            parse_nameservers(
+                "# Generated\nnameserver 192.168.0.1\nnameserver fd00::1\nsearch lan\n"
+            ),
+            vec![
+                "192.168.0.1".parse::<IpAddr>().unwrap(),
+                "fd00::1".parse::<IpAddr>().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_latest_handshakes() {
+        assert_eq!(
+            parse_latest_handshakes("wg0\tpeerA=\t1700000000\nwg0\tpeerB=\t0\n"),
+            vec![
+                Tunnel::WireGuard {
+                    interface: "wg0".into(),
+                    peer: "peerA=".into(),
+                    latest_handshake: Some(
+                        (UNIX_EPOCH + Duration::from_secs(1_700_000_000)).into()
+                    ),
+                },
+                Tunnel::WireGuard {
+                    interface: "wg0".into(),
+                    peer: "peerB=".into(),
+                    latest_handshake: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::pacman::{package_versions, pending_updates};
+use crate::timestamp::Timestamp;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{metadata, read_to_string};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+const PACMAN_LOG: &str = "/var/log/pacman.log";
+/// Packages whose versions are reported.
+const KEY_PACKAGES: [&str; 7] = [
+    "linux",
+    "linux-lts",
+    "chromium",
+    "digsigctl",
+    "application-html",
+    "application-air",
+    "mesa",
+];
+/// Maximum amount of full-system upgrades to report.
+const MAX_FULL_UPGRADES: usize = 10;
+
+/// Maximum age of the collected inventory, since new updates may have been released.
+const MAX_AGE: Duration = Duration::from_hours(1);
+
+/// The last collected inventory, the modification time of the pacman log it is based on
+/// and the time it was collected at.
+///
+/// Every pacman transaction is logged, so the inventory is only collected anew when the log
+/// has changed or it is older than [`MAX_AGE`], since checking for updates is expensive.
+static CACHE: Mutex<Option<(SystemTime, Instant, Packages)>> = Mutex::new(None);
+
+/// Inventory of the installed software packages.
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct Packages {
+    versions: Option<HashMap<String, String>>,
+    /// Amount of available updates, as checked at most an hour ago.
+    pending_updates: Option<usize>,
+    last_full_upgrade: Option<Timestamp>,
+    /// The latest full-system upgrades, oldest first.
+    full_upgrades: Vec<FullUpgrade>,
+}
+
+impl Default for Packages {
+    fn default() -> Self {
+        let Ok(modified) = metadata(PACMAN_LOG).and_then(|metadata| metadata.modified()) else {
+            return Self::collect();
+        };
+        let Ok(mut cache) = CACHE.lock() else {
+            return Self::collect();
+        };
+
+        match cache.as_ref() {
+            Some((log_modified, collected_at, packages))
+                if *log_modified == modified && collected_at.elapsed() < MAX_AGE =>
+            {
+                packages.clone()
+            }
+            _ => {
+                let packages = Self::collect();
+                cache.replace((modified, Instant::now(), packages.clone()));
+                packages
+            }
+        }
+    }
+}
+
+impl Packages {
+    fn collect() -> Self {
+        let full_upgrades = read_to_string(PACMAN_LOG)
+            .map(|log| parse_log(&log))
+            .unwrap_or_default();
+        Self {
+            versions: package_versions(&KEY_PACKAGES)
+                .map(|packages| {
+                    packages
+                        .into_iter()
+                        .map(|package| (package.name().to_string(), package.version().to_string()))
+                        .collect()
+                })
+                .ok(),
+            pending_updates: pending_updates().map(|updates| updates.len()).ok(),
+            last_full_upgrade: full_upgrades.last().map(|upgrade| upgrade.started_at),
+            full_upgrades: full_upgrades
+                .iter()
+                .rev()
+                .take(MAX_FULL_UPGRADES)
+                .rev()
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// A full-system upgrade as recorded in the pacman log.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct FullUpgrade {
+    started_at: Timestamp,
+    /// Amount of packages that were upgraded.
+    upgraded: usize,
+    /// Whether the transaction completed.
+    completed: bool,
+}
+
+/// Parse the full-system upgrades from the pacman log.
+fn parse_log(log: &str) -> Vec<FullUpgrade> {
+    let mut upgrades = Vec::new();
+    let mut current: Option<FullUpgrade> = None;
+
+    for (time, message) in log.lines().filter_map(parse_line) {
+        if message == "[PACMAN] starting full system upgrade" {
+            upgrades.extend(current.replace(FullUpgrade {
+                started_at: time,
+                upgraded: 0,
+                completed: false,
+            }));
+        } else if message.starts_with("[PACMAN] Running ") {
+            upgrades.extend(current.take());
+        } else if let Some(upgrade) = current.as_mut() {
+            if message.starts_with("[ALPM] upgraded ") {
+                upgrade.upgraded += 1;
+            } else if message == "[ALPM] transaction completed" {
+                upgrade.completed = true;
+            }
+        }
+    }
+
+    upgrades.extend(current);
+    upgrades
+}
+
+/// Parse a line of the pacman log, e.g. `[2024-01-15T10:23:45+0100] [ALPM] transaction completed`.
+fn parse_line(line: &str) -> Option<(Timestamp, &str)> {
+    let (time, message) = line.strip_prefix('[')?.split_once("] ")?;
+    Some((parse_time(time)?, message.trim_end()))
+}
+
+/// Parse a timestamp with a numeric UTC offset, e.g. `2024-01-15T10:23:45+0100`.
+fn parse_time(time: &str) -> Option<Timestamp> {
+    let (local, offset) = time.split_at_checked(time.len().checked_sub(5)?)?;
+    let local = humantime::parse_rfc3339_weak(local).ok()?;
+    let hours: u64 = offset.get(1..3)?.parse().ok()?;
+    let minutes: u64 = offset.get(3..)?.parse().ok()?;
+    let duration = Duration::from_secs(hours * 3600 + minutes * 60);
+
+    match offset.chars().next()? {
+        '+' => local.checked_sub(duration),
+        '-' => local.checked_add(duration),
+        _ => None,
+    }
+    .map(Timestamp::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_log, parse_time, FullUpgrade};
+    use std::time::{Duration, SystemTime};
+
+    const LOG: &str = "\
+[2024-01-15T10:23:45+0100] [PACMAN] Running 'pacman -Syu'
+[2024-01-15T10:23:45+0100] [PACMAN] synchronizing package lists
+[2024-01-15T10:23:47+0100] [PACMAN] starting full system upgrade
+[2024-01-15T10:24:10+0100] [ALPM] transaction started
+[2024-01-15T10:24:10+0100] [ALPM] upgraded chromium (120.0.6099.224-1 -> 121.0.6167.85-1)
+[2024-01-15T10:24:11+0100] [ALPM] upgraded mesa (1:23.3.3-1 -> 1:23.3.4-1)
+[2024-01-15T10:24:12+0100] [ALPM] transaction completed
+[2024-01-16T08:00:00+0100] [PACMAN] Running 'pacman -S --noconfirm --needed digsigctl'
+[2024-01-16T08:00:01+0100] [ALPM] upgraded digsigctl (0.1.19-1 -> 0.1.20-1)
+[2024-02-01T09:00:00+0100] [PACMAN] Running 'pacman -Syu'
+[2024-02-01T09:00:02+0100] [PACMAN] starting full system upgrade
+";
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            SystemTime::from(parse_time("1970-01-01T01:00:10+0100").unwrap()),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+        );
+        assert_eq!(
+            SystemTime::from(parse_time("1970-01-01T00:00:10-0030").unwrap()),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1810)
+        );
+        assert!(parse_time("2019-01-01 10:00").is_none());
+    }
+
+    #[test]
+    fn test_parse_log() {
+        assert_eq!(
+            parse_log(LOG),
+            vec![
+                FullUpgrade {
+                    started_at: parse_time("2024-01-15T10:23:47+0100").unwrap(),
+                    upgraded: 2,
+                    completed: true,
+                },
+                FullUpgrade {
+                    started_at: parse_time("2024-02-01T09:00:02+0100").unwrap(),
+                    upgraded: 0,
+                    completed: false,
+                },
+            ]
+        );
+    }
+}
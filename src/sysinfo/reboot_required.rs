@@ -0,0 +1,172 @@
+use crate::pacman::package_version;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+use sysinfo::System;
+
+const MODULES_DIR: &str = "/usr/lib/modules";
+const PROC_DIR: &str = "/proc";
+/// Kernel package to assume, if the running kernel's package cannot be determined.
+const DEFAULT_KERNEL_PACKAGE: &str = "linux";
+/// Processes that shall not run with libraries which have been removed by an upgrade.
+const PROCESSES: [&str; 2] = ["chromium", "digsigctl"];
+
+/// A reason why the system needs to be rebooted.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Reason {
+    /// The running kernel differs from the installed one, so its modules may be missing.
+    #[serde(rename = "kernelUpgraded")]
+    KernelUpgraded {
+        running: String,
+        package: String,
+        installed: Option<String>,
+        modules_missing: bool,
+    },
+    /// A process still uses libraries that have been replaced on disk.
+    #[serde(rename = "deletedLibraries")]
+    DeletedLibraries {
+        process: String,
+        libraries: BTreeSet<String>,
+    },
+}
+
+/// Returns the reasons why the system needs to be rebooted.
+///
+/// If the returned vector is empty, no reboot is required.
+pub fn reasons() -> Vec<Reason> {
+    kernel_upgraded()
+        .into_iter()
+        .chain(deleted_libraries())
+        .collect()
+}
+
+fn kernel_upgraded() -> Option<Reason> {
+    let running = System::kernel_version()?;
+    let modules = Path::new(MODULES_DIR).join(&running);
+    let modules_missing = !modules.is_dir();
+    let package = read_to_string(modules.join("pkgbase")).map_or_else(
+        |_| DEFAULT_KERNEL_PACKAGE.to_string(),
+        |pkgbase| pkgbase.trim().to_string(),
+    );
+    let installed = package_version(&package).ok();
+
+    if modules_missing
+        || installed
+            .as_deref()
+            .is_some_and(|version| !is_release(&running, &kernel_release(version)))
+    {
+        Some(Reason::KernelUpgraded {
+            running,
+            package,
+            installed,
+            modules_missing,
+        })
+    } else {
+        None
+    }
+}
+
+fn deleted_libraries() -> Vec<Reason> {
+    let mut libraries: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for entry in read_dir(PROC_DIR).into_iter().flatten().flatten() {
+        let dir = entry.path();
+        let Ok(process) = read_to_string(dir.join("comm")) else {
+            continue;
+        };
+        let process = process.trim();
+
+        if !PROCESSES.contains(&process) {
+            continue;
+        }
+
+        if let Ok(maps) = read_to_string(dir.join("maps")) {
+            let deleted = deleted_mapped_libraries(&maps);
+
+            if !deleted.is_empty() {
+                libraries
+                    .entry(process.to_string())
+                    .or_default()
+                    .extend(deleted);
+            }
+        }
+    }
+
+    libraries
+        .into_iter()
+        .map(|(process, libraries)| Reason::DeletedLibraries { process, libraries })
+        .collect()
+}
+
+/// Returns the kernel release as reported by `uname -r` for the given version of a kernel package.
+///
+/// E.g. `6.7.2.arch1-1` of `linux` yields `6.7.2-arch1-1`, and `6.6.14-1` of `linux-lts`
+/// yields `6.6.14-1`, which is a prefix of the actual release `6.6.14-1-lts`.
+fn kernel_release(version: &str) -> String {
+    let (pkgver, pkgrel) = version.rsplit_once('-').unwrap_or((version, ""));
+
+    match pkgver.rsplit_once('.') {
+        Some((base, suffix)) if suffix.starts_with(|char: char| char.is_ascii_alphabetic()) => {
+            format!("{base}-{suffix}-{pkgrel}")
+        }
+        _ => format!("{pkgver}-{pkgrel}"),
+    }
+}
+
+/// Returns whether the running kernel is of the given release, ignoring a flavour suffix like `-lts`.
+fn is_release(running: &str, release: &str) -> bool {
+    running
+        .strip_prefix(release)
+        .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('-'))
+}
+
+/// Returns the shared libraries from a process' memory map, that have been deleted on disk.
+fn deleted_mapped_libraries(maps: &str) -> BTreeSet<String> {
+    maps.lines()
+        .filter_map(|line| {
+            // The path follows the address, permissions, offset, device and inode.
+            let path = line.splitn(6, char::is_whitespace).nth(5)?.trim();
+            let path = path.strip_suffix(" (deleted)")?;
+            path.contains(".so").then(|| path.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deleted_mapped_libraries, is_release, kernel_release};
+
+    #[test]
+    fn test_kernel_release() {
+        assert_eq!(kernel_release("6.7.2.arch1-1"), "6.7.2-arch1-1");
+        assert_eq!(kernel_release("6.6.14-1"), "6.6.14-1");
+    }
+
+    #[test]
+    fn test_is_release() {
+        assert!(is_release("6.7.2-arch1-1", "6.7.2-arch1-1"));
+        assert!(is_release("6.6.14-1-lts", "6.6.14-1"));
+        assert!(!is_release("6.7.2-arch1-10", "6.7.2-arch1-1"));
+        assert!(!is_release("6.6.14-1", "6.6.1-1"));
+    }
+
+    #[test]
+    fn test_deleted_mapped_libraries() {
+        let maps = "\
+55d0c0a00000-55d0c0a02000 r--p 00000000 103:02 1234 /usr/lib/chromium/chromium
+7f0e1c000000-7f0e1c021000 rw-p 00000000 00:00 0
+7f0e1d200000-7f0e1d228000 r--p 00000000 103:02 5678 /usr/lib/libc.so.6 (deleted)
+7f0e1d400000-7f0e1d428000 r--p 00000000 103:02 5679 /usr/lib/libdrm.so.2.4.0
+7f0e1d500000-7f0e1d501000 rw-s 00000000 00:01 42 /memfd:shared (deleted)
+";
+        assert_eq!(
+            deleted_mapped_libraries(maps)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["/usr/lib/libc.so.6".to_string()]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use rocket::serde::json::serde_json;
 use std::process::{Command, Stdio};
 
-const SENSORS: &str = "/usr/bin/sensors";
+pub const SENSORS: &str = "/usr/bin/sensors";
 const JSON: &str = "-j";
 
 /// Collect information about local sensors.
@@ -1,10 +1,10 @@
 use crate::sudo::sudo;
 use crate::try_from_io::TryFromIo;
-use rocket::log::private::error;
 use std::collections::HashMap;
 use std::process::{Child, Stdio};
+use tracing::error;
 
-const SMARTCTL: &str = "/usr/bin/smartctl";
+pub const SMARTCTL: &str = "/usr/bin/smartctl";
 const SMART_STATUS_PREFIX: &str = "SMART overall-health self-assessment test result:";
 
 /// Returns a child process running `smartctl`.
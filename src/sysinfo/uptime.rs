@@ -1,18 +1,32 @@
 use std::time::{Duration, SystemTime};
 
+use rocket::serde::json::Value;
+use schemars::JsonSchema;
 use serde::Serialize;
 use sysinfo::{LoadAvg, System, Users};
 
 /// Uptime information of the system.
-#[derive(Debug, Serialize)]
+#[derive(Debug, JsonSchema, Serialize)]
 pub struct Uptime {
     time: SystemTime,
     #[allow(clippy::struct_field_names)]
     uptime: Duration,
+    #[schemars(with = "Vec<Value>")]
     users: Users,
+    #[schemars(with = "LoadAvgSchema")]
     load_avg: LoadAvg,
 }
 
+/// Schema of [`LoadAvg`] as serialized by `sysinfo`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(rename = "LoadAvg")]
+struct LoadAvgSchema {
+    one: f64,
+    five: f64,
+    fifteen: f64,
+}
+
 impl Default for Uptime {
     fn default() -> Self {
         Self {
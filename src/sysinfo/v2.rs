@@ -0,0 +1,238 @@
+//! Version 2 of the system information.
+//!
+//! In contrast to the original representation, all timestamps are given in RFC 3339 format,
+//! all quantities carry their unit in the field name and all fields are named in `snake_case`.
+
+use super::application::{self, Metadata};
+use super::cmdline::cmdline;
+use super::cpuinfo::CpuInfo;
+use super::efi::Efi;
+use super::meminfo::memory_bytes;
+use super::mount::root_mounted_ro;
+use super::network::Network;
+use super::packages::Packages;
+use super::reboot_required::{self, Reason};
+use super::sensors::sensors;
+use super::smart::device_states;
+use crate::integrity::{self, Verification};
+use crate::reconcile::{drift_events, Drift};
+use crate::rpc::operation_mode::{Inconsistency, OperationMode};
+use crate::timestamp::Timestamp;
+use rocket::serde::json::serde_json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env::consts::OS;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use sysinfo::{Disks, System, Users};
+use tracing::warn;
+
+/// Version of this schema.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Collected information about the local digital signage system the program is running on.
+#[derive(Debug, JsonSchema, Serialize)]
+#[schemars(rename = "SystemInformationV2")]
+pub struct SystemInformation {
+    schema_version: u32,
+    collected_at: Timestamp,
+    os: &'static str,
+    application: Metadata,
+    cpu: Option<CpuInfo>,
+    efi: Efi,
+    kernel_cmdline: Option<HashMap<String, Option<String>>>,
+    disks: Vec<Disk>,
+    memory_bytes: Option<HashMap<String, usize>>,
+    root_read_only: Option<bool>,
+    sensors: Option<serde_json::Value>,
+    uptime: Uptime,
+    smart_status: Option<HashMap<String, Option<String>>>,
+    drift: Vec<DriftEvent>,
+    packages: Packages,
+    reboot_required: bool,
+    reboot_reasons: Vec<Reason>,
+    file_integrity: Option<Verification>,
+    operation_state: OperationState,
+    network: Network,
+}
+
+impl Default for SystemInformation {
+    fn default() -> Self {
+        let reboot_reasons = reboot_required::reasons();
+        let operation_state = crate::rpc::operation_mode::OperationState::get();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            collected_at: Timestamp::now(),
+            os: OS,
+            application: application::status(&operation_state),
+            cpu: CpuInfo::read().ok(),
+            efi: Efi::default(),
+            kernel_cmdline: cmdline().ok(),
+            disks: Disks::new_with_refreshed_list()
+                .list()
+                .iter()
+                .filter_map(|disk| {
+                    Disk::try_from(disk)
+                        .inspect_err(|_| warn!("Invalid disk: {disk:?}"))
+                        .ok()
+                })
+                .collect(),
+            memory_bytes: memory_bytes().ok(),
+            root_read_only: root_mounted_ro().ok(),
+            sensors: sensors().ok(),
+            uptime: Uptime::default(),
+            smart_status: device_states().ok(),
+            drift: drift_events().iter().map(DriftEvent::from).collect(),
+            packages: Packages::default(),
+            reboot_required: !reboot_reasons.is_empty(),
+            reboot_reasons,
+            file_integrity: integrity::latest(),
+            operation_state: OperationState::from(&operation_state),
+            network: Network::default(),
+        }
+    }
+}
+
+/// Usage of a mounted filesystem.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+pub struct Disk {
+    filesystem: String,
+    mountpoint: PathBuf,
+    total_bytes: u64,
+    used_bytes: u64,
+    available_bytes: u64,
+    used_percent: u8,
+}
+
+impl TryFrom<&sysinfo::Disk> for Disk {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(disk: &sysinfo::Disk) -> Result<Self, Self::Error> {
+        let total_bytes = disk.total_space();
+        let used_bytes = total_bytes.saturating_sub(disk.available_space());
+        (used_bytes * 100)
+            .checked_div(total_bytes)
+            .unwrap_or_default()
+            .try_into()
+            .map(|used_percent| Self {
+                filesystem: String::from_utf8_lossy(disk.file_system().as_encoded_bytes())
+                    .to_string(),
+                mountpoint: disk.mount_point().into(),
+                total_bytes,
+                used_bytes,
+                available_bytes: disk.available_space(),
+                used_percent,
+            })
+    }
+}
+
+/// Uptime and load of the system.
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "UptimeV2")]
+pub struct Uptime {
+    boot_time: Timestamp,
+    #[allow(clippy::struct_field_names)]
+    uptime_seconds: u64,
+    load_average: LoadAverage,
+    users: Vec<String>,
+}
+
+impl Default for Uptime {
+    fn default() -> Self {
+        let uptime_seconds = System::uptime();
+        let load_average = System::load_average();
+        Self {
+            boot_time: SystemTime::now()
+                .checked_sub(Duration::from_secs(uptime_seconds))
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+                .into(),
+            uptime_seconds,
+            load_average: LoadAverage {
+                one: load_average.one,
+                five: load_average.five,
+                fifteen: load_average.fifteen,
+            },
+            users: Users::new_with_refreshed_list()
+                .iter()
+                .map(|user| user.name().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Average amount of runnable processes over the last one, five and fifteen minutes.
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+pub struct LoadAverage {
+    one: f64,
+    five: f64,
+    fifteen: f64,
+}
+
+/// A detected drift from the desired state and whether it has been corrected.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "DriftEventV2")]
+pub struct DriftEvent {
+    detected_at: Timestamp,
+    #[serde(flatten)]
+    drift: Drift,
+    corrected: bool,
+    error: Option<String>,
+}
+
+impl From<&crate::reconcile::DriftEvent> for DriftEvent {
+    fn from(event: &crate::reconcile::DriftEvent) -> Self {
+        Self {
+            detected_at: event.time().into(),
+            drift: event.drift().clone(),
+            corrected: event.corrected(),
+            error: event.error().map(ToString::to_string),
+        }
+    }
+}
+
+/// Detailed state of the operation mode of the system.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "OperationStateV2")]
+pub struct OperationState {
+    operation_mode: OperationMode,
+    consistent: bool,
+    services: Vec<ServiceState>,
+    inconsistencies: Vec<Inconsistency>,
+}
+
+impl From<&crate::rpc::operation_mode::OperationState> for OperationState {
+    fn from(state: &crate::rpc::operation_mode::OperationState) -> Self {
+        Self {
+            operation_mode: state.operation_mode().clone(),
+            consistent: state.consistent(),
+            services: state.services().iter().map(ServiceState::from).collect(),
+            inconsistencies: state.inconsistencies().to_vec(),
+        }
+    }
+}
+
+/// State of the service of an operation mode.
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq, Serialize)]
+#[schemars(rename = "ServiceStateV2")]
+pub struct ServiceState {
+    service: &'static str,
+    operation_mode: OperationMode,
+    enabled: bool,
+    active: bool,
+    failed: bool,
+    failure_reason: Option<String>,
+}
+
+impl From<&crate::rpc::operation_mode::ServiceState> for ServiceState {
+    fn from(service: &crate::rpc::operation_mode::ServiceState) -> Self {
+        Self {
+            service: service.service(),
+            operation_mode: service.operation_mode().clone(),
+            enabled: service.enabled(),
+            active: service.active(),
+            failed: service.failed(),
+            failure_reason: service.failure_reason().map(ToString::to_string),
+        }
+    }
+}
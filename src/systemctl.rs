@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
+use tracing::info;
 
 /// Starts the given service.
 ///
@@ -80,12 +82,48 @@ pub fn status(service: &str) -> subprocess::Result<ExitStatus> {
     systemctl(&["status", service])
 }
 
+/// Returns the given properties of the given service.
+///
+/// # Errors
+///
+/// This function will return a [`subprocess::PopenError`] if the subprocess fails.
+pub fn show(service: &str, properties: &[&str]) -> subprocess::Result<HashMap<String, String>> {
+    let mut popen = Popen::create(
+        &[
+            "systemctl",
+            "show",
+            "--property",
+            &properties.join(","),
+            service,
+        ],
+        PopenConfig {
+            stdout: Redirection::Pipe,
+            stderr: Redirection::Pipe,
+            detached: false,
+            ..Default::default()
+        },
+    )?;
+    let (stdout, _) = popen.communicate(None)?;
+    popen.wait()?;
+    Ok(stdout
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect())
+}
+
 /// Runs a systemctl subcommand with administrative privileges using `sudo`.
 ///
 /// # Errors
 ///
 /// This function will return a [`subprocess::PopenError`] if the subprocess fails.
 fn systemctl_adm(command: &[&str]) -> subprocess::Result<ExitStatus> {
+    info!(
+        unit = command.last().copied().unwrap_or_default(),
+        "Running systemctl {}",
+        command.join(" ")
+    );
     Popen::create(
         &[&["sudo", "systemctl"], command].concat(),
         PopenConfig {
@@ -0,0 +1,88 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+/// A point in time, serialized as an RFC 3339 timestamp in UTC.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp(SystemTime);
+
+impl Timestamp {
+    /// Returns the current point in time.
+    #[must_use]
+    pub fn now() -> Self {
+        Self(SystemTime::now())
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self(time)
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&humantime::format_rfc3339_seconds(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        humantime::parse_rfc3339(&String::deserialize(deserializer)?)
+            .map(Self)
+            .map_err(D::Error::custom)
+    }
+}
+
+impl JsonSchema for Timestamp {
+    fn schema_name() -> Cow<'static, str> {
+        "Timestamp".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "date-time",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use rocket::serde::json::serde_json;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_serialize_rfc3339() {
+        assert_eq!(
+            serde_json::to_string(&Timestamp::from(
+                UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+            ))
+            .unwrap(),
+            r#""2023-11-14T22:13:20Z""#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rfc3339() {
+        assert_eq!(
+            serde_json::from_str::<Timestamp>(r#""2023-11-14T22:13:20Z""#).unwrap(),
+            Timestamp::from(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+    }
+}